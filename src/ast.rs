@@ -0,0 +1,150 @@
+//! Parsing of TIS-100 assembly code that keeps the source.
+//!
+//! `parse_program` only produces the instructions of a program, so labels, comments, blank lines
+//! and line numbers are lost. `parse_source` produces a `SourceProgram` instead, which keeps every
+//! line of the source along with what was parsed from it, and maps each program counter back to
+//! the line of its instruction.
+
+use core::{Program, Instruction};
+use lex::{lex_program, Label, Line, Span, NUM_CHARS};
+use parse::{parse_program, ProgramErrors};
+
+/// A line of a program's source.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SourceLine {
+    /// The index of the line in the program, starting from 0, as in `ProgramErrors`.
+    pub index: usize,
+    /// The text of the line, as it was written.
+    pub text: String,
+    /// The label that the line defines, in upper case, the way that jumps refer to it.
+    pub label: Option<String>,
+    /// The text after the `#` on the line, without surrounding whitespace. Like the rest of the
+    /// line, only the first `NUM_CHARS` characters are read, so a `#` after them isn't a comment.
+    pub comment: Option<String>,
+    /// The instruction on the line.
+    pub instruction: Option<Instruction>,
+    /// Where the instruction is on the line, from its opcode to the end of its last operand.
+    pub span: Option<Span>,
+    /// The index of the instruction in the program.
+    pub pc: Option<usize>,
+}
+
+/// A parsed program that keeps every line of its source.
+///
+/// # Example
+///
+/// ```
+/// use tis_100::ast::parse_source;
+/// use tis_100::core::Instruction::*;
+/// use tis_100::core::Source::*;
+/// use tis_100::core::Register::*;
+/// use tis_100::parse::parse_program;
+///
+/// let src = "# Double the input\nSTART: MOV UP ACC\n\nADD ACC # x2\nMOV ACC DOWN\n";
+/// let source = parse_source(src).unwrap();
+///
+/// assert_eq!(source.program(), parse_program(src).unwrap());
+/// assert_eq!(source.source_map(), vec![1, 3, 4]);
+///
+/// let line = source.line(1).unwrap();
+/// assert_eq!(line.instruction, Some(Add(REG(ACC))));
+/// assert_eq!(line.comment, Some("x2".to_string()));
+///
+/// let span = line.span.unwrap();
+/// assert_eq!(&line.text[span.byte_start..span.byte_end], "ADD ACC");
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct SourceProgram {
+    pub lines: Vec<SourceLine>,
+}
+
+impl SourceProgram {
+    /// Get the instructions of the program, the same as `parse_program` would.
+    pub fn program(&self) -> Program {
+        self.lines.iter().filter_map(|line| line.instruction).collect()
+    }
+
+    /// Get the index of the line of each instruction, by program counter.
+    pub fn source_map(&self) -> Vec<usize> {
+        self.lines.iter().filter(|line| line.pc.is_some()).map(|line| line.index).collect()
+    }
+
+    /// Get the line of the instruction at the given program counter.
+    pub fn line(&self, pc: usize) -> Option<&SourceLine> {
+        self.lines.iter().find(|line| line.pc == Some(pc))
+    }
+}
+
+/// Parse the program source code into a `SourceProgram`. If one or more errors are encountered
+/// during parsing, then the same errors as `parse_program` will be returned instead. Like
+/// `parse_program`, only the first `NUM_LINES` lines are part of the program.
+pub fn parse_source(src: &str) -> Result<SourceProgram, ProgramErrors> {
+    let mut instructions = parse_program(src)?.into_iter();
+    let mut lines = Vec::new();
+    let mut pc = 0;
+
+    for (Line(index, label, words, spans), text) in lex_program(src).into_iter().zip(src.lines()) {
+        let instruction = if words.is_empty() { None } else { instructions.next() };
+        let end = text.char_indices().nth(NUM_CHARS).map_or(text.len(), |(byte, _)| byte);
+        let read = &text[..end];
+        let span = match (spans.first(), spans.last()) {
+            (Some(first), Some(&last)) => Some(first.to(last)),
+            _ => None,
+        };
+
+        lines.push(SourceLine {
+            index: index,
+            text: text.to_string(),
+            label: label.map(|Label(name, _, _)| name),
+            comment: read.find('#').map(|start| read[start + 1..].trim().to_string()),
+            instruction: instruction,
+            span: instruction.and(span),
+            pc: instruction.map(|_| pc),
+        });
+
+        if instruction.is_some() {
+            pc += 1;
+        }
+    }
+
+    Ok(SourceProgram {
+        lines: lines,
+    })
+}
+
+#[test]
+fn test_parse_source() {
+    use core::Instruction::*;
+
+    let src = "\nL:\n  NEG # flip\n# only a comment\nLOOP: JMP L,, #\n";
+    let source = parse_source(src).unwrap();
+
+    assert_eq!(source.lines.len(), 5);
+    assert_eq!(source.lines[0].text, "");
+    assert_eq!(source.lines[1].label, Some("L".to_string()));
+    assert_eq!(source.lines[1].instruction, None);
+
+    let neg = &source.lines[2];
+    assert_eq!((neg.instruction, neg.pc), (Some(Neg), Some(0)));
+    assert_eq!(neg.span.map(|span| (span.start, span.end)), Some((2, 5)));
+    assert_eq!(neg.comment, Some("flip".to_string()));
+
+    assert_eq!(source.lines[3].comment, Some("only a comment".to_string()));
+    assert_eq!(source.lines[3].span, None);
+
+    let jump = &source.lines[4];
+    assert_eq!((jump.instruction, jump.pc), (Some(Jmp(0)), Some(1)));
+    assert_eq!(jump.span.map(|span| (span.start, span.end)), Some((6, 11)));
+    assert_eq!(jump.comment, Some("".to_string()));
+
+    assert_eq!(source.source_map(), vec![2, 4]);
+    assert_eq!(source.line(1).map(|line| line.index), Some(4));
+    assert_eq!(source.line(2), None);
+
+    assert!(parse_source("JMP NOWHERE\n").is_err());
+
+    // A # past the characters that the lexer reads doesn't start a comment.
+    let source = parse_source("MOV UP DOWN # long comment\nMOV UP, DOWN,,,,,,# ignored\n").unwrap();
+    assert_eq!(source.lines[0].comment, Some("long".to_string()));
+    assert_eq!(source.lines[1].comment, None);
+}
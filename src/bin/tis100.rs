@@ -0,0 +1,9 @@
+extern crate tis_100;
+
+use std::env;
+use std::process;
+use tis_100::cli;
+
+fn main() {
+    process::exit(cli::main(env::args().collect()));
+}
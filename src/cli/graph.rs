@@ -0,0 +1,85 @@
+//! The `graph` subcommand, which draws the tiles and connections of a puzzle as a Graphviz graph.
+
+use std::fs::File;
+use std::io::Write;
+use getopts::Options;
+use dot::Graph;
+use machine::Puzzle;
+use spec::PuzzleSpec;
+use super::{parse, load_save, load_spec, spec_error, EXIT_USAGE, EXIT_INPUT};
+use super::source::read_file;
+
+/// Print a DOT graph of a puzzle with a save, or write it to a file.
+pub fn graph(program: &str, args: &[String]) -> i32 {
+    let mut opts = Options::new();
+    opts.optflag("w", "weights", "Run the puzzle and label each connection with the values that crossed it");
+    opts.optopt("s", "seed", "Run the puzzle with the streams for this seed", "SEED");
+    opts.optopt("o", "output", "Write the graph to a file instead of printing it", "FILE");
+
+    let brief = format!("Usage: {} graph [options] <spec.lua|spec.toml|spec.json|segment> <save.txt>", program);
+    let matches = match parse(&mut opts, args, &brief, 2) {
+        Ok(matches) => matches,
+        Err(code) => return code,
+    };
+
+    let src = match read_file(&matches.free[1]) {
+        Ok(src) => src,
+        Err(code) => return code,
+    };
+
+    let save = match load_save(&matches.free[1]) {
+        Ok(save) => save,
+        Err(code) => return code,
+    };
+
+    let mut spec = match load_spec(&matches.free[0], save) {
+        Ok(spec) => spec,
+        Err(code) => return code,
+    };
+
+    if let Some(seed) = matches.opt_str("seed") {
+        match seed.parse::<u32>() {
+            Ok(seed) => if let Err(error) = spec.set_seed(seed) {
+                return spec_error(error);
+            },
+            Err(_) => {
+                eprintln!("Invalid seed: {}", seed);
+                return EXIT_USAGE;
+            },
+        }
+    }
+
+    let layout = spec.layout();
+    let streams = spec.test_streams().to_vec();
+    let mut puzzle = match Puzzle::from_spec(&mut spec) {
+        Ok(puzzle) => puzzle,
+        Err(error) => return spec_error(error),
+    };
+
+    let profile = if matches.opt_present("weights") {
+        puzzle.set_profiling(true);
+        puzzle.run();
+        puzzle.profile()
+    } else {
+        None
+    };
+
+    let mut graph = Graph::new(puzzle.cpu(), &layout, &streams).source(&src);
+    if let Some(ref profile) = profile {
+        graph = graph.profile(profile);
+    }
+
+    match matches.opt_str("output") {
+        Some(filename) => match File::create(&filename).and_then(|mut file| write!(file, "{}", graph)) {
+            Ok(_) => 0,
+            Err(_) => {
+                eprintln!("Could not write graph file");
+                EXIT_INPUT
+            },
+        },
+        None => {
+            print!("{}", graph);
+            0
+        },
+    }
+}
@@ -0,0 +1,192 @@
+//! The `tis100` command-line tool. Each subcommand takes its own options, which are described by
+//! `tis100 <command> --help`.
+//!
+//! The `puzzle` and `sandbox` binaries are thin wrappers around the `run` and `sandbox`
+//! subcommands.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::env;
+//! use std::process;
+//! use tis_100::cli;
+//!
+//! process::exit(cli::main(env::args().collect()));
+//! ```
+
+use std::thread;
+use std::time::Duration;
+use getopts::{Options, Matches};
+use save::{self, Save};
+use diagnostic::save_diagnostics;
+use spec::{Spec, SpecError, library};
+
+mod run;
+mod source;
+mod graph;
+mod sandbox;
+
+/// The exit code used when the command-line arguments are invalid.
+pub const EXIT_USAGE: i32 = 64;
+
+/// The exit code used when a save or spec file can't be loaded.
+pub const EXIT_INPUT: i32 = 65;
+
+/// A subcommand of the `tis100` tool. It receives the program name and the arguments that
+/// follow the subcommand, and returns the exit code.
+struct Command {
+    name: &'static str,
+    summary: &'static str,
+    run: fn(&str, &[String]) -> i32,
+}
+
+const COMMANDS: &'static [Command] = &[
+    Command { name: "run", summary: "Run a puzzle and report the outcome", run: run::run },
+    Command { name: "check", summary: "Check that a save file parses and fits a puzzle", run: source::check },
+    Command { name: "score", summary: "Run a puzzle and print its cycles, nodes and instructions", run: run::score },
+    Command { name: "trace", summary: "Run a puzzle and print the registers of each node per cycle", run: run::trace },
+    Command { name: "coverage", summary: "Run a puzzle and report which lines and branches executed", run: run::coverage },
+    Command { name: "graph", summary: "Draw the tiles and connections of a puzzle as a Graphviz graph", run: graph::graph },
+    Command { name: "fmt", summary: "Format a save file", run: source::fmt },
+    Command { name: "lint", summary: "Warn about code that the game would truncate or ignore", run: source::lint },
+    Command { name: "sandbox", summary: "Run a save in the Simple Sandbox with a console", run: sandbox::sandbox },
+];
+
+/// Run the `tis100` tool with the given arguments, including the program name, and return the
+/// exit code.
+pub fn main(args: Vec<String>) -> i32 {
+    let program = args.first().cloned().unwrap_or("tis100".to_string());
+
+    let name = match args.get(1) {
+        Some(name) => name.as_str(),
+        None => {
+            print_commands(&program);
+            return EXIT_USAGE;
+        },
+    };
+
+    if name == "-h" || name == "--help" || name == "help" {
+        print_commands(&program);
+        return 0;
+    }
+
+    match COMMANDS.iter().find(|command| command.name == name) {
+        Some(command) => (command.run)(&program, &args[2..]),
+        None => {
+            eprintln!("Unknown command: {}\n", name);
+            print_commands(&program);
+            EXIT_USAGE
+        },
+    }
+}
+
+/// Print the list of subcommands.
+fn print_commands(program: &str) {
+    println!("TIS-100 Emulator\n\nUsage:\n    {} <command> [options]\n\nCommands:", program);
+
+    for command in COMMANDS.iter() {
+        println!("    {:<10}{}", command.name, command.summary);
+    }
+}
+
+/// Parse the options for a subcommand. If the options are invalid or `--help` was given, the usage
+/// is printed and the exit code is returned as an error.
+fn parse(opts: &mut Options, args: &[String], brief: &str, num_free: usize) -> Result<Matches, i32> {
+    opts.optflag("h", "help", "Print this help message");
+
+    let matches = match opts.parse(args) {
+        Ok(matches) => matches,
+        Err(err) => {
+            eprintln!("{}\n", err);
+            eprintln!("{}", opts.usage(brief));
+            return Err(EXIT_USAGE);
+        },
+    };
+
+    if matches.opt_present("help") {
+        println!("{}", opts.usage(brief));
+        return Err(0);
+    }
+
+    if matches.free.len() < num_free {
+        eprintln!("{}", opts.usage(brief));
+        return Err(EXIT_USAGE);
+    }
+
+    Ok(matches)
+}
+
+/// Add the `--speed` and `--no-delay` options.
+fn delay_options(opts: &mut Options) {
+    opts.optopt("", "speed", "Limit execution to this many cycles per second", "HZ");
+    opts.optflag("", "no-delay", "Run as fast as possible");
+}
+
+/// Get the delay between cycles from the `--speed` and `--no-delay` options.
+fn delay(matches: &Matches, default: Option<Duration>) -> Result<Option<Duration>, i32> {
+    if matches.opt_present("no-delay") {
+        return Ok(None);
+    }
+
+    match matches.opt_str("speed") {
+        Some(speed) => match speed.parse::<u64>() {
+            Ok(hz) if hz > 0 => Ok(Some(Duration::from_nanos(1_000_000_000 / hz))),
+            _ => {
+                eprintln!("Invalid speed: {}", speed);
+                Err(EXIT_USAGE)
+            },
+        },
+        None => Ok(default),
+    }
+}
+
+/// Sleep for the delay between cycles, if there is one.
+fn wait(delay: Option<Duration>) {
+    if let Some(delay) = delay {
+        thread::sleep(delay);
+    }
+}
+
+/// Load and parse a save file, printing a diagnostic for each error.
+fn load_save(filename: &str) -> Result<Save, i32> {
+    let src = source::read_file(filename)?;
+
+    match save::parse_save(&src) {
+        Ok(save) => Ok(save),
+        Err(errs) => {
+            eprintln!("Could not parse save file");
+            for diagnostic in save_diagnostics(filename, &src, &errs) {
+                eprintln!("{}", diagnostic);
+            }
+            Err(EXIT_INPUT)
+        },
+    }
+}
+
+/// Load a spec from a file, or the spec for one of the campaign puzzles, printing any errors.
+fn load_spec(name: &str, save: Save) -> Result<Spec, i32> {
+    // The spec can either be a file or the segment ID of one of the campaign puzzles.
+    let maybe_spec = if library::find(name).is_some() {
+        Spec::from_segment(name, save)
+    } else {
+        Spec::from_file(name, save)
+    };
+
+    maybe_spec.map_err(spec_error)
+}
+
+/// Print an error from loading a spec or generating its streams, and get the exit code for it.
+fn spec_error(error: SpecError) -> i32 {
+    let message = match error {
+        SpecError::SeedRandomFailed => "Could not seed random number generator",
+        SpecError::ReadFileFailed => "Could not load spec file",
+        SpecError::GetLayoutFailed => "Could not load layout from spec file",
+        SpecError::GetStreamsFailed => "Could not load streams from spec file",
+        SpecError::DecodeFailed => "Could not decode spec file",
+        SpecError::BudgetExceeded => "Spec file exceeded its execution budget",
+        _ => "Could not load spec file",
+    };
+
+    eprintln!("{}", message);
+    EXIT_INPUT
+}
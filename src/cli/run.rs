@@ -0,0 +1,493 @@
+//! The `run`, `score` and `trace` subcommands, which execute a save against a puzzle.
+
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::BufWriter;
+use std::rc::Rc;
+use std::time::Duration;
+use getopts::{Options, Matches};
+use serde_json;
+use machine::{Puzzle, PuzzleReport, Outcome, DEFAULT_MAX_CYCLES, NUM_NODES};
+use profile::Profile;
+use coverage::Coverage;
+use vcd::VcdWriter;
+use save::{Save, instruction_count, node_count};
+use spec::{Spec, SpecError};
+use super::{parse, delay_options, delay, wait, load_save, load_spec, spec_error, EXIT_USAGE, EXIT_INPUT};
+use super::source::read_file;
+
+/// The result of running the puzzle with a single seed.
+#[derive(Serialize)]
+struct Run {
+    seed: Option<u32>,
+    #[serde(flatten)]
+    report: PuzzleReport,
+    profile: Option<Profile>,
+    #[serde(skip)]
+    coverage: Option<Coverage>,
+}
+
+/// The result of running the puzzle with every seed, printed by `run --format json`.
+#[derive(Serialize)]
+struct Summary {
+    outcome: Outcome,
+    cycles: usize,
+    instructions: usize,
+    nodes: usize,
+    seeds: Vec<u32>,
+    runs: Vec<Run>,
+}
+
+/// The score of a solution, printed by `score --format json`.
+#[derive(Serialize)]
+struct Score {
+    outcome: Outcome,
+    cycles: usize,
+    nodes: usize,
+    instructions: usize,
+}
+
+/// The coverage of a solution over every seed, printed by `coverage --format json`.
+#[derive(Serialize)]
+struct CoverageSummary {
+    outcome: Outcome,
+    seeds: Vec<u32>,
+    instructions_executed: usize,
+    instructions: usize,
+    branches_taken: usize,
+    branches: usize,
+    #[serde(flatten)]
+    coverage: Coverage,
+}
+
+/// The registers of a single node in a `trace`.
+#[derive(Serialize)]
+struct NodeTrace {
+    node: usize,
+    #[serde(flatten)]
+    registers: ::node::Registers,
+}
+
+/// The registers of every node with a program after a cycle, printed by `trace --format json`.
+#[derive(Serialize)]
+struct CycleTrace {
+    cycle: usize,
+    nodes: Vec<NodeTrace>,
+}
+
+/// The exit code for each outcome, so that scripts can check the result without parsing it.
+pub fn exit_code(outcome: Outcome) -> i32 {
+    match outcome {
+        Outcome::Passed => 0,
+        Outcome::Failed => 1,
+        Outcome::Deadlock => 2,
+        Outcome::Timeout => 3,
+        Outcome::Halt => 4,
+        Outcome::Livelock => 5,
+    }
+}
+
+/// The options that are shared by every subcommand that runs a puzzle.
+struct RunOptions {
+    spec: Spec,
+    save: Save,
+    seeds: Vec<u32>,
+    max_cycles: Option<usize>,
+    detect_livelock: bool,
+    profile: bool,
+    coverage: bool,
+    delay: Option<Duration>,
+    json: bool,
+    quiet: bool,
+}
+
+/// Add the options that are shared by every subcommand that runs a puzzle.
+fn run_options(opts: &mut Options) {
+    opts.optopt("c", "max-cycles", &format!("Stop after this many cycles, or never if 0 (default: {})", DEFAULT_MAX_CYCLES), "N");
+    opts.optmulti("s", "seed", "Run the puzzle with the streams for this seed; may be repeated", "SEED");
+    opts.optopt("f", "format", "Print the result as text or json (default: text)", "FORMAT");
+    opts.optflag("q", "quiet", "Don't print anything; only set the exit code");
+    opts.optflag("", "detect-livelock", "Stop once the whole system repeats a previous state");
+    delay_options(opts);
+}
+
+/// Read the options that are shared by every subcommand that runs a puzzle, and load the spec and
+/// save that they name.
+fn read_run_options(matches: &Matches) -> Result<RunOptions, i32> {
+    let max_cycles = match matches.opt_str("max-cycles").map(|s| s.parse::<usize>()) {
+        Some(Ok(0)) => None,
+        Some(Ok(max)) => Some(max),
+        Some(Err(_)) => {
+            eprintln!("Invalid cycle limit");
+            return Err(EXIT_USAGE);
+        },
+        None => Some(DEFAULT_MAX_CYCLES),
+    };
+
+    let mut seeds = Vec::new();
+    for seed in matches.opt_strs("seed") {
+        match seed.parse::<u32>() {
+            Ok(seed) => seeds.push(seed),
+            Err(_) => {
+                eprintln!("Invalid seed: {}", seed);
+                return Err(EXIT_USAGE);
+            },
+        }
+    }
+
+    let json = match matches.opt_str("format").as_deref() {
+        None | Some("text") => false,
+        Some("json") => true,
+        Some(format) => {
+            eprintln!("Invalid format: {}", format);
+            return Err(EXIT_USAGE);
+        },
+    };
+
+    let save = load_save(&matches.free[1])?;
+    let spec = load_spec(&matches.free[0], save.clone())?;
+
+    // Without any seeds, run the streams that were generated when the spec was loaded.
+    if seeds.is_empty() {
+        seeds.extend(spec.seed());
+    }
+
+    Ok(RunOptions {
+        spec: spec,
+        save: save,
+        seeds: seeds,
+        max_cycles: max_cycles,
+        detect_livelock: matches.opt_present("detect-livelock"),
+        profile: false,
+        coverage: false,
+        delay: delay(matches, None)?,
+        json: json,
+        quiet: matches.opt_present("quiet"),
+    })
+}
+
+impl RunOptions {
+    /// Create a puzzle for the given seed. Specs with fixed streams don't use a seed.
+    fn puzzle(&self, seed: Option<u32>) -> Result<Puzzle, SpecError> {
+        let mut puzzle = Puzzle::new(&self.spec, &self.save, seed.unwrap_or(0))?;
+
+        puzzle.set_max_cycles(self.max_cycles);
+        puzzle.set_livelock_detection(self.detect_livelock);
+        puzzle.set_profiling(self.profile);
+        puzzle.set_coverage(self.coverage);
+        Ok(puzzle)
+    }
+
+    /// Run the puzzle with every seed, or once if the spec has fixed streams. Fails if the streams
+    /// can't be generated for one of the seeds.
+    fn runs(&self) -> Result<Vec<Run>, SpecError> {
+        let seeds = if self.seeds.is_empty() {
+            vec![None]
+        } else {
+            self.seeds.iter().map(|&seed| Some(seed)).collect()
+        };
+
+        seeds.into_iter().map(|seed| {
+            let mut puzzle = self.puzzle(seed)?;
+
+            while puzzle.outcome().is_none() {
+                puzzle.step();
+                wait(self.delay);
+            }
+
+            Ok(Run {
+                seed: seed,
+                report: puzzle.report(),
+                profile: puzzle.profile(),
+                coverage: puzzle.coverage(),
+            })
+        }).collect()
+    }
+}
+
+/// The puzzle only passes if it passes with every seed.
+fn combined_outcome(runs: &[Run]) -> Outcome {
+    runs.iter()
+        .filter_map(|run| run.report.outcome)
+        .find(|&outcome| outcome != Outcome::Passed)
+        .unwrap_or(Outcome::Passed)
+}
+
+/// The largest number of cycles of any run.
+fn combined_cycles(runs: &[Run]) -> usize {
+    runs.iter().map(|run| run.report.cycles).max().unwrap_or(0)
+}
+
+/// Get the text for an outcome.
+fn outcome_text(outcome: Outcome) -> &'static str {
+    match outcome {
+        Outcome::Passed => "PASSED",
+        Outcome::Failed => "FAILED",
+        Outcome::Deadlock => "DEADLOCK",
+        Outcome::Timeout => "TIMEOUT",
+        Outcome::Halt => "HALTED",
+        Outcome::Livelock => "LIVELOCK",
+    }
+}
+
+/// Run a puzzle and report the outcome for every seed.
+pub fn run(program: &str, args: &[String]) -> i32 {
+    let mut opts = Options::new();
+    run_options(&mut opts);
+    opts.optflag("p", "profile", "Print how busy each node was, and the busiest connections");
+
+    let brief = format!("Usage: {} run [options] <spec.lua|spec.toml|spec.json|segment> <save.txt>", program);
+    let matches = match parse(&mut opts, args, &brief, 2) {
+        Ok(matches) => matches,
+        Err(code) => return code,
+    };
+
+    let mut options = match read_run_options(&matches) {
+        Ok(options) => options,
+        Err(code) => return code,
+    };
+    options.profile = matches.opt_present("profile");
+
+    let runs = match options.runs() {
+        Ok(runs) => runs,
+        Err(error) => return spec_error(error),
+    };
+    let outcome = combined_outcome(&runs);
+
+    if options.quiet {
+        return exit_code(outcome);
+    }
+
+    if options.json {
+        let summary = Summary {
+            outcome: outcome,
+            cycles: combined_cycles(&runs),
+            instructions: instruction_count(&options.save),
+            nodes: node_count(&options.save),
+            seeds: options.seeds,
+            runs: runs,
+        };
+
+        println!("{}", serde_json::to_string_pretty(&summary).unwrap());
+    } else {
+        for run in runs.iter() {
+            if runs.len() > 1 {
+                if let Some(seed) = run.seed {
+                    println!("SEED: {}", seed);
+                }
+            }
+
+            match run.report.outcome {
+                Some(Outcome::Failed) | Some(Outcome::Deadlock) | Some(Outcome::Livelock) => print!("{}", run.report),
+                Some(Outcome::Passed) | Some(Outcome::Timeout) => {
+                    println!("{}", outcome_text(run.report.outcome.unwrap()));
+                    println!("CYCLES: {}", run.report.cycles);
+                },
+                Some(outcome) => println!("{}", outcome_text(outcome)),
+                None => (),
+            }
+
+            if let Some(ref profile) = run.profile {
+                println!();
+                print!("{}", profile);
+            }
+        }
+    }
+
+    exit_code(outcome)
+}
+
+/// Run a puzzle with every seed and print the score of the solution, like the histograms in the
+/// game.
+pub fn score(program: &str, args: &[String]) -> i32 {
+    let mut opts = Options::new();
+    run_options(&mut opts);
+
+    let brief = format!("Usage: {} score [options] <spec.lua|spec.toml|spec.json|segment> <save.txt>", program);
+    let options = match parse(&mut opts, args, &brief, 2).and_then(|m| read_run_options(&m)) {
+        Ok(options) => options,
+        Err(code) => return code,
+    };
+
+    let runs = match options.runs() {
+        Ok(runs) => runs,
+        Err(error) => return spec_error(error),
+    };
+    let score = Score {
+        outcome: combined_outcome(&runs),
+        cycles: combined_cycles(&runs),
+        nodes: node_count(&options.save),
+        instructions: instruction_count(&options.save),
+    };
+
+    if options.quiet {
+        return exit_code(score.outcome);
+    }
+
+    if options.json {
+        println!("{}", serde_json::to_string_pretty(&score).unwrap());
+    } else if score.outcome == Outcome::Passed {
+        println!("CYCLES: {}", score.cycles);
+        println!("NODES: {}", score.nodes);
+        println!("INSTRUCTIONS: {}", score.instructions);
+    } else {
+        println!("{}", outcome_text(score.outcome));
+    }
+
+    exit_code(score.outcome)
+}
+
+/// Run a puzzle with every seed and report how many times each instruction executed, and how many
+/// times each conditional jump was taken or not taken, by save file line.
+pub fn coverage(program: &str, args: &[String]) -> i32 {
+    let mut opts = Options::new();
+    run_options(&mut opts);
+
+    let brief = format!("Usage: {} coverage [options] <spec.lua|spec.toml|spec.json|segment> <save.txt>", program);
+    let matches = match parse(&mut opts, args, &brief, 2) {
+        Ok(matches) => matches,
+        Err(code) => return code,
+    };
+
+    let mut options = match read_run_options(&matches) {
+        Ok(options) => options,
+        Err(code) => return code,
+    };
+    options.coverage = true;
+
+    let src = match read_file(&matches.free[1]) {
+        Ok(src) => src,
+        Err(code) => return code,
+    };
+
+    let runs = match options.runs() {
+        Ok(runs) => runs,
+        Err(error) => return spec_error(error),
+    };
+    let outcome = combined_outcome(&runs);
+
+    let mut coverage = Coverage { nodes: Vec::new() };
+    for run in runs.iter() {
+        if let Some(ref run_coverage) = run.coverage {
+            coverage.merge(run_coverage);
+        }
+    }
+    coverage.map_lines(&src);
+
+    if options.quiet {
+        return exit_code(outcome);
+    }
+
+    if options.json {
+        let (instructions_executed, instructions) = coverage.instructions();
+        let (branches_taken, branches) = coverage.branches();
+        let summary = CoverageSummary {
+            outcome: outcome,
+            seeds: options.seeds,
+            instructions_executed: instructions_executed,
+            instructions: instructions,
+            branches_taken: branches_taken,
+            branches: branches,
+            coverage: coverage,
+        };
+
+        println!("{}", serde_json::to_string_pretty(&summary).unwrap());
+    } else {
+        println!("{}", outcome_text(outcome));
+        print!("{}", coverage);
+    }
+
+    exit_code(outcome)
+}
+
+/// Run a puzzle with a single seed and print the registers of every node with a program after
+/// each cycle.
+pub fn trace(program: &str, args: &[String]) -> i32 {
+    let mut opts = Options::new();
+    run_options(&mut opts);
+    opts.optopt("", "vcd", "Also write a waveform of the registers and the bus to a VCD file", "FILE");
+
+    let brief = format!("Usage: {} trace [options] <spec.lua|spec.toml|spec.json|segment> <save.txt>", program);
+    let matches = match parse(&mut opts, args, &brief, 2) {
+        Ok(matches) => matches,
+        Err(code) => return code,
+    };
+
+    let options = match read_run_options(&matches) {
+        Ok(options) => options,
+        Err(code) => return code,
+    };
+
+    let nodes = (0..NUM_NODES)
+        .filter(|&node| options.save.get(node).map_or(false, |prog| !prog.is_empty()))
+        .collect::<Vec<_>>();
+
+    let mut puzzle = match options.puzzle(options.seeds.first().cloned()) {
+        Ok(puzzle) => puzzle,
+        Err(error) => return spec_error(error),
+    };
+
+    let vcd = match matches.opt_str("vcd") {
+        Some(filename) => match File::create(&filename) {
+            Ok(file) => {
+                let vcd = Rc::new(RefCell::new(VcdWriter::new(BufWriter::new(file), puzzle.cpu())));
+                puzzle.add_hook(vcd.clone());
+                Some(vcd)
+            },
+            Err(_) => {
+                eprintln!("Could not create VCD file");
+                return EXIT_INPUT;
+            },
+        },
+        None => None,
+    };
+
+    let outcome = loop {
+        if let Some(outcome) = puzzle.outcome() {
+            break outcome;
+        }
+
+        puzzle.step();
+        wait(options.delay);
+
+        if options.quiet {
+            continue;
+        }
+
+        let trace = CycleTrace {
+            cycle: puzzle.cycles(),
+            nodes: nodes.iter().filter_map(|&node| {
+                puzzle.registers(node).map(|registers| NodeTrace {
+                    node: node,
+                    registers: registers,
+                })
+            }).collect(),
+        };
+
+        if options.json {
+            println!("{}", serde_json::to_string(&trace).unwrap());
+        } else {
+            println!("CYCLE {}", trace.cycle);
+            for node in trace.nodes.iter() {
+                let registers = &node.registers;
+                println!("  NODE {:>2}  {:<4}  PC {:>2}  ACC {:>4}  BAK {:>4}",
+                         node.node, format!("{:?}", registers.mode).to_uppercase(), registers.pc,
+                         registers.acc, registers.bak);
+            }
+        }
+    };
+
+    if !options.quiet && !options.json {
+        println!("{}", outcome_text(outcome));
+    }
+
+    if let Some(vcd) = vcd {
+        let written = vcd.borrow_mut().flush();
+        if written.is_err() {
+            eprintln!("Could not write VCD file");
+            return EXIT_INPUT;
+        }
+    }
+
+    exit_code(outcome)
+}
@@ -0,0 +1,87 @@
+//! The `sandbox` subcommand, which runs a save in the *Simple Sandbox* puzzle with a console.
+
+use std::io;
+use std::thread;
+use std::time::Duration;
+use std::sync::mpsc::channel;
+use std::sync::mpsc::TryRecvError::*;
+use getopts::Options;
+use machine::Sandbox;
+use super::{parse, delay_options, delay, wait, load_save};
+
+/// Run a save in the sandbox. Values typed on stdin are written to the console input, and values
+/// written to the console output are printed.
+pub fn sandbox(program: &str, args: &[String]) -> i32 {
+    let mut opts = Options::new();
+    delay_options(&mut opts);
+
+    let brief = format!("Usage: {} sandbox [options] <save.txt>", program);
+    let matches = match parse(&mut opts, args, &brief, 1) {
+        Ok(matches) => matches,
+        Err(code) => return code,
+    };
+
+    let delay = match delay(&matches, Some(Duration::from_millis(1))) {
+        Ok(delay) => delay,
+        Err(code) => return code,
+    };
+
+    let save = match load_save(&matches.free[0]) {
+        Ok(save) => save,
+        Err(code) => return code,
+    };
+
+    // Channels for communicating from the command-line to the TIS-100
+    let (in_tx, in_rx) = channel();
+    let (out_tx, out_rx) = channel();
+
+    // TIS-100 loop
+    thread::spawn(move|| {
+        let mut tis100 = Sandbox::from_save(&save);
+
+        loop {
+            match in_rx.try_recv() {
+                Ok(val) => tis100.write_console(val),
+                Err(Disconnected) => break,
+                _ => (),
+            };
+
+            tis100.step();
+
+            if let Some(val) = tis100.read_console() {
+                if let Err(_) = out_tx.send(val) {
+                    break;
+                }
+            }
+
+            wait(delay);
+        }
+
+        drop(in_rx);
+        drop(out_tx);
+    });
+
+    // Console output loop
+    thread::spawn(move|| {
+        for val in out_rx.iter() {
+            println!("> {}", val);
+        }
+    });
+
+    // Console input loop
+    let stdin = io::stdin();
+    loop {
+        let mut input = String::new();
+        match stdin.read_line(&mut input) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => if let Ok(val) = str::parse::<isize>(input.trim_end()) {
+                if let Err(_) = in_tx.send(val) {
+                    break;
+                }
+            },
+        }
+    }
+
+    drop(in_tx);
+    0
+}
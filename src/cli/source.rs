@@ -0,0 +1,258 @@
+//! The `check`, `fmt` and `lint` subcommands, which work on the source of a save file without
+//! running it.
+
+use std::fs::File;
+use std::io::{Read, Write};
+use getopts::Options;
+use lex::{lex_program, Line, Label, NUM_CHARS};
+use machine::NUM_NODES;
+use save::split_nodes;
+use spec::{PuzzleSpec, Tile};
+use strict::{check_save, StrictError, GAME_LINES};
+use super::{parse, load_save, load_spec, EXIT_INPUT};
+
+/// Check that a save file parses, that its programs are on compute tiles of the puzzle, and that
+/// it fits within the line and character limits of the game.
+pub fn check(program: &str, args: &[String]) -> i32 {
+    let mut opts = Options::new();
+    opts.optflag("", "lenient", "Don't check the line and character limits of the game");
+
+    let brief = format!("Usage: {} check [options] <save.txt> [<spec.lua|spec.toml|spec.json|segment>]", program);
+    let matches = match parse(&mut opts, args, &brief, 1) {
+        Ok(matches) => matches,
+        Err(code) => return code,
+    };
+
+    let filename = &matches.free[0];
+    let save = match load_save(filename) {
+        Ok(save) => save,
+        Err(code) => return code,
+    };
+
+    let src = match read_file(filename) {
+        Ok(src) => src,
+        Err(code) => return code,
+    };
+
+    let layout = match matches.free.get(1) {
+        Some(name) => match load_spec(name, save.clone()) {
+            Ok(spec) => spec.layout(),
+            Err(code) => return code,
+        },
+        None => vec![Tile::Compute; NUM_NODES],
+    };
+
+    let mut errors = check_save(&src, &layout);
+
+    if matches.opt_present("lenient") {
+        // Without the limits, only programs that would run on the wrong tile are errors.
+        errors.retain(|&(node, _, ref error)| match *error {
+            StrictError::LineTooLong(_) | StrictError::TooManyLines(_) => false,
+            StrictError::NodeOutsideLayout => save.get(node).map_or(false, |prog| !prog.is_empty()),
+            StrictError::ProgramOnTile(_) => true,
+        });
+    }
+
+    for &(node, line, ref error) in errors.iter() {
+        println!("{}:{}: Node {}: {}", filename, line, node, error);
+    }
+
+    if errors.is_empty() {
+        println!("OK");
+        0
+    } else {
+        1
+    }
+}
+
+/// Format a save file, printing the result or writing it back to the file.
+pub fn fmt(program: &str, args: &[String]) -> i32 {
+    let mut opts = Options::new();
+    opts.optflag("w", "write", "Write the formatted save back to the file");
+    opts.optflag("", "check", "Don't print anything; exit with 1 if the save isn't formatted");
+
+    let brief = format!("Usage: {} fmt [options] <save.txt>", program);
+    let matches = match parse(&mut opts, args, &brief, 1) {
+        Ok(matches) => matches,
+        Err(code) => return code,
+    };
+
+    let filename = &matches.free[0];
+    let src = match read_file(filename) {
+        Ok(src) => src,
+        Err(code) => return code,
+    };
+
+    let formatted = format_save(&src);
+
+    if matches.opt_present("check") {
+        if formatted == src { 0 } else { 1 }
+    } else if matches.opt_present("write") {
+        match File::create(filename).and_then(|mut file| file.write_all(formatted.as_bytes())) {
+            Ok(_) => 0,
+            Err(_) => {
+                eprintln!("Could not write save file");
+                EXIT_INPUT
+            },
+        }
+    } else {
+        print!("{}", formatted);
+        0
+    }
+}
+
+/// Print warnings for code in a save file that the game would truncate or ignore.
+pub fn lint(program: &str, args: &[String]) -> i32 {
+    let mut opts = Options::new();
+    let brief = format!("Usage: {} lint [options] <save.txt>", program);
+    let matches = match parse(&mut opts, args, &brief, 1) {
+        Ok(matches) => matches,
+        Err(code) => return code,
+    };
+
+    let filename = &matches.free[0];
+    let src = match read_file(filename) {
+        Ok(src) => src,
+        Err(code) => return code,
+    };
+
+    let warnings = lint_save(&src);
+    for &(line, ref message) in warnings.iter() {
+        println!("{}:{}: {}", filename, line, message);
+    }
+
+    if warnings.is_empty() { 0 } else { 1 }
+}
+
+/// Read the text of a save file.
+pub fn read_file(filename: &str) -> Result<String, i32> {
+    let mut src = String::new();
+    match File::open(filename).and_then(|mut file| file.read_to_string(&mut src)) {
+        Ok(_) => Ok(src),
+        Err(_) => {
+            eprintln!("Could not load save file");
+            Err(EXIT_INPUT)
+        },
+    }
+}
+
+/// Format the source of a save file. Instructions and labels are uppercased, and operands are
+/// separated by single spaces. Comments are left as they are. Lines that are longer than the game
+/// allows are never changed, since that could change which code is used.
+fn format_save(src: &str) -> String {
+    let mut formatted = String::new();
+
+    for line in src.lines() {
+        formatted.push_str(&format_line(line));
+        formatted.push('\n');
+    }
+
+    formatted
+}
+
+/// Format a single line of a save file.
+fn format_line(line: &str) -> String {
+    if line.trim().starts_with("@") {
+        return line.trim().to_string();
+    }
+
+    let (code, comment) = match line.find('#') {
+        Some(index) => (&line[..index], &line[index..]),
+        None => (line, ""),
+    };
+
+    let (label, instruction) = match code.find(':') {
+        Some(index) => (Some(code[..index].trim()), &code[index + 1..]),
+        None => (None, code),
+    };
+
+    let instruction = instruction.split(&[' ', ','][..])
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let mut formatted = match label {
+        Some(label) if instruction.is_empty() => format!("{}:", label),
+        Some(label) => format!("{}: {}", label, instruction),
+        None => instruction,
+    }.to_uppercase();
+
+    // The game only reads the first characters of a line, so a line that is truncated, or that
+    // would be truncated once it is formatted, is left alone.
+    if formatted.len() > NUM_CHARS || code.trim_end().len() > NUM_CHARS {
+        return line.trim_end().to_string();
+    }
+
+    if !comment.is_empty() {
+        if !formatted.is_empty() {
+            formatted.push(' ');
+        }
+        formatted.push_str(comment.trim_end());
+    }
+
+    formatted
+}
+
+/// Find the code in a save file that the game would truncate or ignore, and labels that are never
+/// used. Each warning has the line number that it refers to. Lines and programs are measured the
+/// same way as `check_save`, so comments count towards the limits.
+fn lint_save(src: &str) -> Vec<(usize, String)> {
+    let mut warnings = Vec::new();
+
+    for (node, header, node_src) in split_nodes(src) {
+        if node >= NUM_NODES {
+            warnings.push((header, format!("Node {} doesn't exist", node)));
+        }
+
+        for (index, line) in node_src.lines().enumerate() {
+            let line_num = header + 1 + index;
+            if index >= GAME_LINES {
+                if !line.trim().is_empty() {
+                    warnings.push((line_num, format!("Node {}: The game only allows {} lines in a node", node, GAME_LINES)));
+                    break;
+                }
+            } else if line.trim_end().chars().count() > NUM_CHARS {
+                warnings.push((line_num, format!("Node {}: The game only allows {} characters on a line", node, NUM_CHARS)));
+            }
+        }
+
+        let lines = lex_program(&node_src);
+        for &Line(index, ref label, _, _) in lines.iter() {
+            if let Some(Label(ref name, _, _)) = *label {
+                let used = lines.iter().any(|&Line(_, _, ref words, _)| words.iter().skip(1).any(|word| word == name));
+                if !name.is_empty() && !used {
+                    warnings.push((header + 1 + index, format!("Node {}: The label {} is never used", node, name)));
+                }
+            }
+        }
+    }
+
+    warnings.sort_by_key(|&(line, _)| line);
+    warnings
+}
+
+#[test]
+fn test_format_save() {
+    let src = "@0\nloop:mov up,  down  # Keep this\n  # Comment\nL:\n\n@1\nJMP LOOP\n";
+    let expected = "@0\nLOOP: MOV UP DOWN # Keep this\n# Comment\nL:\n\n@1\nJMP LOOP\n";
+    assert_eq!(format_save(src), expected);
+    assert_eq!(format_save(expected), expected);
+
+    // Adding a space after the label would push the last operand past the end of the line.
+    assert_eq!(format_save("@0\nLABEL:MOV LEFT,ACC\n"), "@0\nLABEL:MOV LEFT,ACC\n");
+}
+
+#[test]
+fn test_lint_save() {
+    let src = "@0\nL: MOV UP DOWN\nM: JMP L\nMOV LEFT RIGHT # MOV 1 ACC\nMOV RIGHT, LEFT # fits\nMOV RIGHT,     LEFT\n@12\nNOP\n";
+    let warnings = lint_save(src);
+    let lines = warnings.iter().map(|&(line, _)| line).collect::<Vec<_>>();
+    assert_eq!(lines, vec![3, 4, 5, 6, 7]);
+    assert!(warnings[0].1.contains("label M"));
+
+    // The limits are the same as the ones used by check_save.
+    let src = format!("@0\n{}# 16\n", "NOP\n".repeat(GAME_LINES));
+    let layout = vec![Tile::Compute];
+    assert_eq!(lint_save(&src).iter().map(|&(line, _)| line).collect::<Vec<_>>(), vec![17]);
+    assert_eq!(check_save(&src, &layout).iter().map(|&(_, line, _)| line).collect::<Vec<_>>(), vec![17]);
+}
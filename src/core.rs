@@ -1,9 +1,10 @@
 //! Basic types for parsing and interpreting TIS-100 assembly code.
 
 use std::str::FromStr;
+use std::fmt::{Display, Formatter, Error};
 
 /// A TIS-100 port.
-#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash, Serialize)]
 pub enum Port {
     UP,
     DOWN,
@@ -31,6 +32,12 @@ impl FromStr for Port {
     }
 }
 
+impl Display for Port {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "{:?}", self)
+    }
+}
+
 /// Get the opposing direction for a given port.
 ///
 /// # Example
@@ -81,6 +88,16 @@ impl FromStr for IoRegister {
     }
 }
 
+impl Display for IoRegister {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match *self {
+            DIR(port) => write!(f, "{}", port),
+            ANY => f.write_str("ANY"),
+            LAST => f.write_str("LAST"),
+        }
+    }
+}
+
 /// A TIS-100 register.
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum Register {
@@ -113,6 +130,16 @@ impl FromStr for Register {
     }
 }
 
+impl Display for Register {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match *self {
+            ACC => f.write_str("ACC"),
+            NIL => f.write_str("NIL"),
+            IO(reg) => write!(f, "{}", reg),
+        }
+    }
+}
+
 /// The source component of a TIS-100 instruction.
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum Source {
@@ -140,6 +167,15 @@ impl FromStr for Source {
     }
 }
 
+impl Display for Source {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match *self {
+            VAL(val) => write!(f, "{}", val),
+            REG(reg) => write!(f, "{}", reg),
+        }
+    }
+}
+
 /// A valid TIS-100 instruction.
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum Instruction {
@@ -158,6 +194,65 @@ pub enum Instruction {
     Jro(Source),
 }
 
+use self::Instruction::*;
+
+/// Formats the instruction as TIS-100 assembly. Jumps refer to their target by the label that
+/// `jump_label` gives it.
+///
+/// # Example
+///
+/// ```
+/// use tis_100::core::Instruction::*;
+/// use tis_100::core::Source::*;
+/// use tis_100::core::Register::*;
+/// use tis_100::core::IoRegister::*;
+///
+/// assert_eq!(Mov(REG(IO(ANY)), ACC).to_string(), "MOV ANY ACC");
+/// assert_eq!(Add(VAL(-5)).to_string(), "ADD -5");
+/// assert_eq!(Jez(2).to_string(), "JEZ C");
+/// ```
+impl Display for Instruction {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match *self {
+            Nop => f.write_str("NOP"),
+            Mov(src, dst) => write!(f, "MOV {} {}", src, dst),
+            Swp => f.write_str("SWP"),
+            Sav => f.write_str("SAV"),
+            Add(src) => write!(f, "ADD {}", src),
+            Sub(src) => write!(f, "SUB {}", src),
+            Neg => f.write_str("NEG"),
+            Jmp(index) => write!(f, "JMP {}", jump_label(index)),
+            Jez(index) => write!(f, "JEZ {}", jump_label(index)),
+            Jnz(index) => write!(f, "JNZ {}", jump_label(index)),
+            Jgz(index) => write!(f, "JGZ {}", jump_label(index)),
+            Jlz(index) => write!(f, "JLZ {}", jump_label(index)),
+            Jro(src) => write!(f, "JRO {}", src),
+        }
+    }
+}
+
+impl Instruction {
+    /// Get the index of the instruction that a jump refers to. `JRO` is relative, so it has no
+    /// target.
+    pub fn jump_target(&self) -> Option<isize> {
+        match *self {
+            Jmp(index) | Jez(index) | Jnz(index) | Jgz(index) | Jlz(index) => Some(index),
+            _ => None,
+        }
+    }
+}
+
+/// Get the label for the instruction at the given index: `A` for the first instruction, `B` for
+/// the second, and so on. Single letters keep labeled lines within the 18 character limit of the
+/// game. Indices that don't fit in the alphabet are labeled `L` followed by the index.
+pub fn jump_label(index: isize) -> String {
+    if (0..26).contains(&index) {
+        ((b'A' + index as u8) as char).to_string()
+    } else {
+        format!("L{}", index)
+    }
+}
+
 /// The list of instructions created by parsing the program source code. The
 /// instructions can then be evaluated by a basic execution node.
 pub type Program = Vec<Instruction>;
@@ -190,6 +285,32 @@ fn test_parse_register() {
     assert_eq!(str::parse::<Register>("bad"), Err(ParseRegisterError));
 }
 
+#[test]
+fn test_display_instruction() {
+    let instructions = ["NOP", "MOV RIGHT NIL", "MOV -999 LAST", "SWP", "SAV", "ADD ACC", "SUB 1", "NEG",
+                        "JRO ANY"];
+
+    for src in instructions.iter() {
+        let words = src.split(' ').collect::<Vec<_>>();
+        let instruction = match words[0] {
+            "NOP" => Nop,
+            "MOV" => Mov(words[1].parse().unwrap(), words[2].parse().unwrap()),
+            "SWP" => Swp,
+            "SAV" => Sav,
+            "ADD" => Add(words[1].parse().unwrap()),
+            "SUB" => Sub(words[1].parse().unwrap()),
+            "NEG" => Neg,
+            _ => Jro(words[1].parse().unwrap()),
+        };
+        assert_eq!(instruction.to_string(), *src);
+    }
+
+    assert_eq!(Jlz(25).to_string(), "JLZ Z");
+    assert_eq!(Jgz(26).to_string(), "JGZ L26");
+    assert_eq!(Jmp(0).jump_target(), Some(0));
+    assert_eq!(Jro(VAL(1)).jump_target(), None);
+}
+
 #[test]
 fn test_parse_source() {
     assert_eq!(str::parse::<Source>("ACC"), Ok(REG(ACC)));
@@ -0,0 +1,272 @@
+//! Instruction coverage of TIS-100 solutions.
+//!
+//! `Coverage` is collected by a `Tis100` once `Tis100::set_coverage` has been called. It counts how
+//! many times each instruction of every program completed, and how many times each conditional
+//! jump was taken or not taken. The instructions can be mapped back to the lines of the save file
+//! that they were parsed from, and the coverage of several runs, such as the runs of a puzzle with
+//! different seeds, can be merged together.
+
+use std::fmt::{Display, Formatter, Error};
+use vec_map::VecMap;
+use core::{Program, Instruction};
+use core::Instruction::*;
+use io::NodeId;
+use machine::node_name;
+use save::instruction_lines;
+
+/// The number of times that a conditional jump was taken and not taken.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize)]
+pub struct BranchCoverage {
+    pub taken: usize,
+    pub not_taken: usize,
+}
+
+/// The coverage of a single instruction.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct InstructionCoverage {
+    /// The index of the instruction in the node's program.
+    pub index: usize,
+    /// The line of the save file that the instruction was parsed from, if it is known.
+    pub line: Option<usize>,
+    /// The number of times that the instruction completed.
+    pub executed: usize,
+    /// The outcomes of the instruction, if it is a conditional jump.
+    pub branch: Option<BranchCoverage>,
+}
+
+/// The coverage of every instruction of a node's program.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct NodeCoverage {
+    pub node: NodeId,
+    pub instructions: Vec<InstructionCoverage>,
+}
+
+/// The coverage of every node with a program.
+///
+/// # Example
+///
+/// ```
+/// use tis_100::save::{Save, parse_save};
+/// use tis_100::spec::SpecBuilder;
+/// use tis_100::machine::Puzzle;
+///
+/// let spec = SpecBuilder::new()
+///     .input("IN.A", 1, vec![1, 2, 3])
+///     .output("OUT.A", 1, vec![1, 2, 3])
+///     .build(Save::new()).ok().unwrap();
+///
+/// let src = "@1\nMOV UP ACC\nJLZ LOW\nMOV ACC DOWN\nJMP END\nLOW: NOP\nEND: NOP\n@5\nMOV UP DOWN\n@9\nMOV UP DOWN\n";
+/// let save = parse_save(src).unwrap();
+/// let mut puzzle = Puzzle::new(&spec, &save, 0).ok().unwrap();
+/// puzzle.set_coverage(true);
+/// puzzle.run();
+///
+/// let mut coverage = puzzle.coverage().unwrap();
+/// coverage.map_lines(src);
+///
+/// // The input is never negative, so the jump is never taken and line 6 never runs.
+/// assert_eq!(coverage.uncovered_lines(), vec![(1, 6)]);
+/// assert_eq!(coverage.branches(), (1, 2));
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct Coverage {
+    pub nodes: Vec<NodeCoverage>,
+}
+
+impl Coverage {
+    /// Construct a new `Coverage` for the given programs, where nothing has been executed yet.
+    pub fn new(programs: &VecMap<&Program>) -> Coverage {
+        let nodes = programs.iter()
+            .filter(|&(_, prog)| !prog.is_empty())
+            .map(|(node, prog)| NodeCoverage {
+                node: node,
+                instructions: prog.iter().enumerate().map(|(index, instruction)| {
+                    InstructionCoverage {
+                        index: index,
+                        line: None,
+                        executed: 0,
+                        branch: if is_branch(instruction) {
+                            Some(BranchCoverage { taken: 0, not_taken: 0 })
+                        } else {
+                            None
+                        },
+                    }
+                }).collect(),
+            })
+            .collect();
+
+        Coverage {
+            nodes: nodes,
+        }
+    }
+
+    /// Record that `instruction`, at `pc`, completed on a node. `acc` is the value of the node's
+    /// ACC register before the instruction executed, which decides whether a conditional jump was
+    /// taken.
+    pub fn record_instruction(&mut self, node: NodeId, pc: usize, instruction: Instruction, acc: isize) {
+        let coverage = match self.nodes.iter_mut().find(|n| n.node == node) {
+            Some(node) => match node.instructions.get_mut(pc) {
+                Some(coverage) => coverage,
+                None => return,
+            },
+            None => return,
+        };
+
+        coverage.executed += 1;
+
+        if let Some(ref mut branch) = coverage.branch.as_mut() {
+            let taken = match instruction {
+                Jez(_) => acc == 0,
+                Jnz(_) => acc != 0,
+                Jgz(_) => acc > 0,
+                Jlz(_) => acc < 0,
+                _ => return,
+            };
+
+            if taken {
+                branch.taken += 1;
+            } else {
+                branch.not_taken += 1;
+            }
+        }
+    }
+
+    /// Set the save file line of every instruction, using the source of the save file that the
+    /// programs were parsed from.
+    pub fn map_lines(&mut self, src: &str) {
+        let lines = instruction_lines(src);
+
+        for node in self.nodes.iter_mut() {
+            for instruction in node.instructions.iter_mut() {
+                instruction.line = lines.get(node.node).and_then(|l| l.get(instruction.index)).cloned();
+            }
+        }
+    }
+
+    /// Add the counts from another run of the same programs.
+    pub fn merge(&mut self, other: &Coverage) {
+        for other_node in other.nodes.iter() {
+            let node = match self.nodes.iter_mut().find(|n| n.node == other_node.node) {
+                Some(node) => node,
+                None => {
+                    self.nodes.push(other_node.clone());
+                    continue;
+                },
+            };
+
+            for (instruction, other) in node.instructions.iter_mut().zip(other_node.instructions.iter()) {
+                instruction.executed += other.executed;
+                if let (Some(branch), Some(other)) = (instruction.branch.as_mut(), other.branch) {
+                    branch.taken += other.taken;
+                    branch.not_taken += other.not_taken;
+                }
+            }
+        }
+
+        self.nodes.sort_by_key(|node| node.node);
+    }
+
+    /// Get the number of instructions that were executed at least once, and the total number of
+    /// instructions.
+    pub fn instructions(&self) -> (usize, usize) {
+        let instructions = self.nodes.iter().flat_map(|node| node.instructions.iter());
+        let total = instructions.clone().count();
+        let covered = instructions.filter(|instruction| instruction.executed > 0).count();
+        (covered, total)
+    }
+
+    /// Get the number of conditional jump outcomes that happened at least once, and the total
+    /// number of outcomes. Each conditional jump has two outcomes: taken and not taken.
+    pub fn branches(&self) -> (usize, usize) {
+        let branches = self.nodes.iter()
+            .flat_map(|node| node.instructions.iter())
+            .filter_map(|instruction| instruction.branch)
+            .collect::<Vec<_>>();
+        let covered = branches.iter().map(|b| (b.taken > 0) as usize + (b.not_taken > 0) as usize).sum();
+        (covered, branches.len() * 2)
+    }
+
+    /// Get the `(node, line)` of every instruction that never executed. Instructions without a
+    /// known line are left out.
+    pub fn uncovered_lines(&self) -> Vec<(NodeId, usize)> {
+        self.nodes.iter()
+            .flat_map(|node| node.instructions.iter().map(move |instruction| (node.node, instruction)))
+            .filter(|&(_, instruction)| instruction.executed == 0)
+            .filter_map(|(node, instruction)| instruction.line.map(|line| (node, line)))
+            .collect()
+    }
+}
+
+impl Display for Coverage {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        let (covered, total) = self.instructions();
+        let (branches_covered, branches) = self.branches();
+        writeln!(f, "{} OF {} INSTRUCTIONS EXECUTED, {} OF {} BRANCHES TAKEN", covered, total,
+                 branches_covered, branches)?;
+
+        for node in self.nodes.iter() {
+            writeln!(f)?;
+            writeln!(f, "{}", node_name(node.node))?;
+
+            for instruction in node.instructions.iter() {
+                let location = match instruction.line {
+                    Some(line) => format!("LINE {}", line),
+                    None => format!("#{}", instruction.index),
+                };
+
+                write!(f, "  {:<8} {:>9}", location, instruction.executed)?;
+
+                if let Some(branch) = instruction.branch {
+                    write!(f, "  TAKEN {}, NOT TAKEN {}", branch.taken, branch.not_taken)?;
+                }
+
+                if instruction.executed == 0 {
+                    write!(f, "  <- NEVER EXECUTED")?;
+                } else if instruction.branch.map_or(false, |b| b.taken == 0) {
+                    write!(f, "  <- NEVER TAKEN")?;
+                } else if instruction.branch.map_or(false, |b| b.not_taken == 0) {
+                    write!(f, "  <- ALWAYS TAKEN")?;
+                }
+
+                writeln!(f)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Check if an instruction is a conditional jump.
+fn is_branch(instruction: &Instruction) -> bool {
+    match *instruction {
+        Jez(_) | Jnz(_) | Jgz(_) | Jlz(_) => true,
+        _ => false,
+    }
+}
+
+#[test]
+fn test_coverage() {
+    use parse::parse_program;
+
+    let prog = parse_program("L: MOV UP ACC\nJEZ L\nNOP\n").unwrap();
+    let mut programs = VecMap::new();
+    programs.insert(1, &prog);
+
+    let mut coverage = Coverage::new(&programs);
+    coverage.record_instruction(1, 0, prog[0], 0);
+    coverage.record_instruction(1, 1, prog[1], 0);
+    assert_eq!(coverage.instructions(), (2, 3));
+    assert_eq!(coverage.branches(), (1, 2));
+
+    let mut other = Coverage::new(&programs);
+    other.record_instruction(1, 1, prog[1], 5);
+    other.record_instruction(1, 2, prog[2], 5);
+    coverage.merge(&other);
+    assert_eq!(coverage.instructions(), (3, 3));
+    assert_eq!(coverage.branches(), (2, 2));
+    assert_eq!(coverage.nodes[0].instructions[1].branch, Some(BranchCoverage { taken: 1, not_taken: 1 }));
+
+    coverage.map_lines("@1\n\nL: MOV UP ACC\nJEZ L\nNOP\n");
+    assert_eq!(coverage.nodes[0].instructions.iter().map(|i| i.line).collect::<Vec<_>>(),
+               vec![Some(3), Some(4), Some(5)]);
+}
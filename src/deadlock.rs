@@ -0,0 +1,227 @@
+//! Diagnostics for deadlocked TIS-100 systems.
+//!
+//! When every node is stalled, a `DeadlockReport` describes the wait-for graph of the system: each
+//! node that is blocked on a read or a write, the port that it is blocked on, and the node on the
+//! other side of that port. Nodes that wait on each other in a cycle are grouped together, since
+//! those cycles can't be broken by any other node.
+
+use std::fmt::{Display, Formatter, Error};
+use core::Port;
+use io::NodeId;
+use machine::node_name;
+use node::{Node, IoAction};
+
+/// The state of the node on the other side of a blocked port.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PeerState {
+    /// Nothing is connected to the port, or the port leads to an unused input or output.
+    Disconnected,
+    /// The peer executes code, but has no program.
+    Idle,
+    /// The peer is damaged, and never reads or writes.
+    Damaged,
+    /// The peer is executing code.
+    Running,
+    /// The peer is also blocked on a read or a write.
+    Blocked,
+    /// The peer doesn't execute code, such as a memory node or an input that has run out of values.
+    Passive,
+}
+
+impl PeerState {
+    /// Classify a node that is the peer of a blocked port.
+    pub fn of(node: &Node) -> PeerState {
+        if node.is_damaged() {
+            PeerState::Damaged
+        } else if node.blocked_on().is_some() {
+            PeerState::Blocked
+        } else if let Some(registers) = node.registers() {
+            match registers.mode {
+                ::node::Mode::Idle => PeerState::Idle,
+                _ => PeerState::Running,
+            }
+        } else {
+            PeerState::Passive
+        }
+    }
+}
+
+/// An edge in the wait-for graph: a node that is blocked reading from or writing to a port.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct Wait {
+    /// The node that is blocked.
+    pub node: NodeId,
+    /// Whether the node is blocked on a read or a write.
+    pub action: IoAction,
+    /// The port that the node is blocked on.
+    pub port: Port,
+    /// The node on the other side of the port, if there is one.
+    pub peer: Option<NodeId>,
+    /// The state of the peer.
+    pub peer_state: PeerState,
+    /// Whether the node and its peer are part of the same cycle of waits.
+    pub cyclic: bool,
+}
+
+/// The wait-for graph of a deadlocked system.
+///
+/// # Example
+///
+/// ```
+/// use tis_100::core::Port::*;
+/// use tis_100::deadlock::{DeadlockReport, Wait, PeerState};
+/// use tis_100::node::IoAction;
+///
+/// // Two nodes that are both waiting to read from each other.
+/// let wait = |node, port, peer| Wait {
+///     node: node,
+///     action: IoAction::Read,
+///     port: port,
+///     peer: Some(peer),
+///     peer_state: PeerState::Blocked,
+///     cyclic: false,
+/// };
+///
+/// let report = DeadlockReport::new(vec![wait(0, RIGHT, 1), wait(1, LEFT, 0)]);
+/// assert_eq!(report.cycles, vec![vec![0, 1]]);
+/// assert!(report.waits.iter().all(|wait| wait.cyclic));
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct DeadlockReport {
+    /// Every port that a node is blocked on, in order of the nodes.
+    pub waits: Vec<Wait>,
+    /// The groups of nodes that wait on each other in a cycle.
+    pub cycles: Vec<Vec<NodeId>>,
+}
+
+impl DeadlockReport {
+    /// Construct a new `DeadlockReport` from the waits in a system. The cycles are found from the
+    /// waits whose peers are also blocked, and the waits within each cycle are marked as cyclic.
+    pub fn new(mut waits: Vec<Wait>) -> DeadlockReport {
+        let blocked = waits.iter()
+            .filter(|wait| wait.peer_state == PeerState::Blocked)
+            .filter_map(|wait| wait.peer.map(|peer| (wait.node, peer)))
+            .collect::<Vec<_>>();
+
+        // A node is in a cycle with every node that it can reach, and that can reach it back.
+        let reachable = |from: NodeId| {
+            let mut seen = vec![from];
+            let mut index = 0;
+            while index < seen.len() {
+                let node = seen[index];
+                for &(_, to) in blocked.iter().filter(|&&(waiter, _)| waiter == node) {
+                    if !seen.contains(&to) {
+                        seen.push(to);
+                    }
+                }
+                index += 1;
+            }
+            seen
+        };
+
+        let mut nodes = blocked.iter().map(|&(node, _)| node).collect::<Vec<_>>();
+        nodes.sort();
+        nodes.dedup();
+
+        let mut cycles: Vec<Vec<NodeId>> = Vec::new();
+        for &node in nodes.iter() {
+            if cycles.iter().any(|cycle| cycle.contains(&node)) {
+                continue;
+            }
+
+            let mut cycle = reachable(node).into_iter()
+                .filter(|&other| other != node && reachable(other).contains(&node))
+                .collect::<Vec<_>>();
+
+            if !cycle.is_empty() {
+                cycle.push(node);
+                cycle.sort();
+                cycles.push(cycle);
+            }
+        }
+
+        for wait in waits.iter_mut() {
+            wait.cyclic = match wait.peer {
+                Some(peer) => cycles.iter().any(|cycle| cycle.contains(&wait.node) && cycle.contains(&peer)),
+                None => false,
+            };
+        }
+
+        DeadlockReport {
+            waits: waits,
+            cycles: cycles,
+        }
+    }
+}
+
+impl Display for DeadlockReport {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        for wait in self.waits.iter() {
+            let (action, preposition) = match wait.action {
+                IoAction::Read => ("READING", "FROM"),
+                IoAction::Write => ("WRITING", "TO"),
+            };
+
+            let peer = match wait.peer {
+                Some(peer) => node_name(peer),
+                None => "NOTHING".to_string(),
+            };
+
+            let state = match wait.peer_state {
+                PeerState::Disconnected => "WHICH IS NOT CONNECTED",
+                PeerState::Idle => "WHICH HAS NO PROGRAM",
+                PeerState::Damaged => "WHICH IS DAMAGED",
+                PeerState::Running => "WHICH IS RUNNING",
+                PeerState::Blocked => "WHICH IS ALSO BLOCKED",
+                PeerState::Passive => "WHICH IS NOT SENDING OR RECEIVING",
+            };
+
+            write!(f, "{} IS BLOCKED {} {:?} {} {}, {}", node_name(wait.node), action, wait.port,
+                   preposition, peer, state)?;
+
+            if wait.cyclic {
+                write!(f, " (CYCLE)")?;
+            }
+
+            writeln!(f)?;
+        }
+
+        for cycle in self.cycles.iter() {
+            let names = cycle.iter().map(|&node| node_name(node)).collect::<Vec<_>>();
+            writeln!(f, "CYCLIC WAIT BETWEEN {}", names.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_deadlock_report() {
+    use core::Port::*;
+
+    let wait = |node, port, peer: Option<NodeId>, peer_state| Wait {
+        node: node,
+        action: IoAction::Write,
+        port: port,
+        peer: peer,
+        peer_state: peer_state,
+        cyclic: false,
+    };
+
+    // 0 and 1 wait on each other, 2 waits on the cycle without being part of it, and 3 waits on
+    // a damaged node.
+    let report = DeadlockReport::new(vec![
+        wait(0, RIGHT, Some(1), PeerState::Blocked),
+        wait(1, LEFT, Some(0), PeerState::Blocked),
+        wait(2, LEFT, Some(1), PeerState::Blocked),
+        wait(3, DOWN, Some(7), PeerState::Damaged),
+    ]);
+
+    assert_eq!(report.cycles, vec![vec![0, 1]]);
+    assert_eq!(report.waits.iter().map(|wait| wait.cyclic).collect::<Vec<_>>(), vec![true, true, false, false]);
+
+    let text = report.to_string();
+    assert!(text.contains("NODE 3 IS BLOCKED WRITING DOWN TO NODE 7, WHICH IS DAMAGED\n"));
+    assert!(text.contains("CYCLIC WAIT BETWEEN NODE 0, NODE 1\n"));
+}
@@ -0,0 +1,255 @@
+//! Rendering of parse errors as diagnostics, in the style of rustc.
+//!
+//! A `Diagnostic` shows the error message, where the error is, the line of source with carets
+//! under the part of it that is wrong, and a hint when the error looks like a common mistake, such
+//! as a misspelled register or a label that only differs because code is read in upper case.
+
+use std::fmt::{Display, Formatter, Error};
+use io::NodeId;
+use lex::{lex_program, Label, Line, Span};
+use machine::node_name;
+use parse::{ParseProgramError, ProgramErrors, error_spans};
+use parse::ParseProgramError::*;
+use save::{SaveErrors, split_nodes};
+
+/// The registers and pseudo-ports that can be used as operands.
+const REGISTERS: &'static [&'static str] = &["ACC", "NIL", "UP", "DOWN", "LEFT", "RIGHT", "ANY", "LAST"];
+
+/// The opcodes of every instruction.
+const OPCODES: &'static [&'static str] = &["NOP", "MOV", "SWP", "SAV", "ADD", "SUB", "NEG", "JMP", "JEZ", "JNZ",
+                                           "JGZ", "JLZ", "JRO"];
+
+/// A parse error that is ready to be shown to the user.
+///
+/// # Example
+///
+/// ```
+/// use tis_100::diagnostic::save_diagnostics;
+/// use tis_100::save::parse_save;
+///
+/// let src = "@0\nMOV UP ACX\n";
+/// let errors = parse_save(src).unwrap_err();
+/// let diagnostics = save_diagnostics("save.txt", src, &errors);
+///
+/// assert_eq!(diagnostics[0].to_string(), "\
+/// error: Invalid register: 'ACX'
+///  --> save.txt:2:8 (NODE 0)
+///   |
+/// 2 | MOV UP ACX
+///   |        ^^^
+///   = hint: did you mean ACC?
+/// ");
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct Diagnostic {
+    /// The error message.
+    pub message: String,
+    /// The name of the file that has the error.
+    pub file: String,
+    /// The node whose program has the error, if the file is a save file.
+    pub node: Option<NodeId>,
+    /// The line of the file that has the error, starting from 1.
+    pub line: usize,
+    /// The text of the line.
+    pub text: String,
+    /// The part of the line that is wrong.
+    pub span: Span,
+    /// A suggestion for fixing the error.
+    pub hint: Option<String>,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        let line = self.line.to_string();
+        let pad = " ".repeat(line.len());
+
+        writeln!(f, "error: {}", self.message)?;
+        write!(f, "{}--> {}:{}:{}", pad, self.file, self.line, self.span.start + 1)?;
+        if let Some(node) = self.node {
+            write!(f, " ({})", node_name(node))?;
+        }
+        writeln!(f)?;
+
+        writeln!(f, "{} |", pad)?;
+        writeln!(f, "{} | {}", line, self.text.trim_end())?;
+
+        // Keep tabs so that the carets line up with the text above them.
+        let indent = self.text.chars()
+            .take(self.span.start)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+        let carets = "^".repeat((self.span.end - self.span.start).max(1));
+        writeln!(f, "{} | {}{}", pad, indent, carets)?;
+
+        if let Some(ref hint) = self.hint {
+            writeln!(f, "{} = hint: {}", pad, hint)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Get the diagnostics for the errors from parsing the source of a single program.
+pub fn program_diagnostics(file: &str, src: &str, errors: &ProgramErrors) -> Vec<Diagnostic> {
+    let lines = lex_program(src);
+    let labels = lines.iter()
+        .filter_map(|&Line(_, ref label, _, _)| label.as_ref().map(|&Label(ref name, _, _)| name.as_str()))
+        .collect::<Vec<_>>();
+
+    errors.iter().zip(error_spans(src, errors)).map(|(&(index, ref error), span)| {
+        let text = src.lines().nth(index).unwrap_or("");
+        let words = lines.get(index).map_or(&[][..], |&Line(_, _, ref words, _)| &words[..]);
+        let written = text.get(span.byte_start..span.byte_end).unwrap_or("");
+
+        Diagnostic {
+            message: error.to_string(),
+            file: file.to_string(),
+            node: None,
+            line: index + 1,
+            text: text.to_string(),
+            span: span,
+            hint: hint(error, written, words, &labels),
+        }
+    }).collect()
+}
+
+/// Get the diagnostics for the errors from parsing a save file, in order of the nodes.
+pub fn save_diagnostics(file: &str, src: &str, errors: &SaveErrors) -> Vec<Diagnostic> {
+    let nodes = split_nodes(src);
+    let mut diagnostics = Vec::new();
+
+    for (node, node_errors) in errors.iter() {
+        // The last program for a node is the one that is kept, so its errors are the ones reported.
+        let (header, node_src) = match nodes.iter().rev().find(|&&(num, _, _)| num == node) {
+            Some(&(_, header, ref node_src)) => (header, node_src.as_str()),
+            None => (0, ""),
+        };
+
+        for mut diagnostic in program_diagnostics(file, node_src, node_errors) {
+            diagnostic.node = Some(node);
+            diagnostic.line += header;
+            diagnostics.push(diagnostic);
+        }
+    }
+
+    diagnostics
+}
+
+/// Suggest a fix for an error. `written` is the text of the error's span as it was written, and
+/// `words` are the lexemes of its line.
+fn hint(error: &ParseProgramError, written: &str, words: &[String], labels: &[&str]) -> Option<String> {
+    match *error {
+        InvalidLabel => Some("a label needs a name before the ':'".to_string()),
+        DuplicateLabel(ref name) if written != name.as_str() => {
+            Some(format!("code is read in upper case, so '{}' is the same label as '{}'", written, name))
+        },
+        DuplicateLabel(_) => Some("each label can only be defined once in a node".to_string()),
+        UndefinedLabel(ref name) => {
+            if name.parse::<isize>().is_ok() {
+                return Some("jumps go to labels; use JRO to jump by a number of instructions".to_string());
+            }
+
+            let similar = closest(name, labels).map(|label| format!("did you mean {}?", label));
+            if written != name.as_str() {
+                let case = format!("code is read in upper case, so '{}' is the label '{}'", written, name);
+                Some(similar.map_or(case.clone(), |similar| format!("{}; {}", case, similar)))
+            } else {
+                similar
+            }
+        },
+        InvalidOpcode(ref opcode) => {
+            if opcode.contains('\t') {
+                Some("tabs don't separate words; use spaces".to_string())
+            } else if words.get(1).map_or(false, |word| OPCODES.contains(&word.as_str())) {
+                Some(format!("to define the label {}, add a ':' after it", opcode))
+            } else {
+                closest(opcode, OPCODES).map(|opcode| format!("did you mean {}?", opcode))
+            }
+        },
+        InvalidExpression(ref operand) | InvalidRegister(ref operand) => {
+            if operand == "BAK" {
+                Some("BAK can't be used directly; use SWP or SAV".to_string())
+            } else if operand.parse::<isize>().is_ok() {
+                words.first().map(|opcode| usage(opcode))
+            } else {
+                closest(operand, REGISTERS).map(|register| format!("did you mean {}?", register))
+            }
+        },
+        MissingOperand(_) | TooManyOperands(_) => words.first().map(|opcode| usage(opcode)),
+    }
+}
+
+/// Describe the operands that an instruction takes.
+fn usage(opcode: &str) -> String {
+    match opcode {
+        "MOV" => "MOV takes a value or register to read, and a register to write".to_string(),
+        "ADD" | "SUB" | "JRO" => format!("{} takes a value or register", opcode),
+        "JMP" | "JEZ" | "JNZ" | "JGZ" | "JLZ" => format!("{} takes a label", opcode),
+        _ => format!("{} doesn't take any operands", opcode),
+    }
+}
+
+/// Find the candidate that is closest to a word that isn't valid, if any are close enough to be a
+/// likely misspelling.
+fn closest<'a>(word: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    candidates.iter()
+        .map(|&candidate| (edit_distance(word, candidate), candidate))
+        .filter(|&(distance, candidate)| distance <= 2 && distance < candidate.len())
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Count the single character insertions, deletions and substitutions that turn one word into
+/// another.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..b.len() + 1).collect::<Vec<_>>();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+
+        for j in 0..b.len() {
+            let substitution = previous + if ca == b[j] { 0 } else { 1 };
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(previous + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+#[test]
+fn test_edit_distance() {
+    assert_eq!(edit_distance("ACC", "ACC"), 0);
+    assert_eq!(edit_distance("ACX", "ACC"), 1);
+    assert_eq!(edit_distance("MOVE", "MOV"), 1);
+    assert_eq!(edit_distance("RIGTH", "RIGHT"), 2);
+    assert_eq!(edit_distance("", "NIL"), 3);
+    assert_eq!(closest("RIGTH", REGISTERS), Some("RIGHT"));
+    assert_eq!(closest("FOO", REGISTERS), None);
+}
+
+#[test]
+fn test_diagnostic_hints() {
+    use save::parse_save;
+
+    let src = "@0\nloop: MOV UP ACC\njmp Lop\n\n@1\nMOVE 1 ACC\nL MOV 1 ACC\nADD BAK\nMOV 1 2\nNEG 1\n\tJMP\nJMP # L\n";
+    let errors = parse_save(src).unwrap_err();
+    let diagnostics = save_diagnostics("save.txt", src, &errors);
+    let hints = diagnostics.iter().map(|d| (d.node, d.line, d.hint.clone().unwrap_or_default())).collect::<Vec<_>>();
+
+    assert_eq!(hints, vec![
+        (Some(0), 3, "code is read in upper case, so 'Lop' is the label 'LOP'; did you mean LOOP?".to_string()),
+        (Some(1), 6, "did you mean MOV?".to_string()),
+        (Some(1), 7, "to define the label L, add a ':' after it".to_string()),
+        (Some(1), 8, "BAK can't be used directly; use SWP or SAV".to_string()),
+        (Some(1), 9, "MOV takes a value or register to read, and a register to write".to_string()),
+        (Some(1), 10, "NEG doesn't take any operands".to_string()),
+        (Some(1), 11, "tabs don't separate words; use spaces".to_string()),
+        (Some(1), 12, "JMP takes a label".to_string()),
+    ]);
+
+    // A missing operand is pointed at the end of the instruction.
+    assert!(diagnostics[7].to_string().ends_with("12 | JMP # L\n   |    ^\n   = hint: JMP takes a label\n"));
+}
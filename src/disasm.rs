@@ -0,0 +1,96 @@
+//! Conversion of TIS-100 programs back into assembly source.
+//!
+//! The parser resolves labels to the indices of the instructions that they refer to, so the
+//! original labels of a program are lost. The disassembler gives each instruction that is the
+//! target of a jump a new label with `core::jump_label`, which is short enough that every labeled
+//! line fits within the 18 character limit of the game.
+
+use core::{Program, jump_label};
+use lex::NUM_CHARS;
+
+/// Convert a program into TIS-100 assembly source that parses back into the same program. Each
+/// instruction is on its own line, and each line ends with a newline.
+///
+/// A label that doesn't fit on the line of its instruction, or that marks the end of the program,
+/// takes a line of its own, so the source can have more lines than the program has instructions.
+/// Programs from `parse_program` have at most `NUM_LINES` instructions, but they can need more
+/// lines than that once their labels are rebuilt. The lexer ignores the lines past `NUM_LINES`, so
+/// that source doesn't parse back into the same program.
+///
+/// # Example
+///
+/// ```
+/// use tis_100::disasm::disassemble;
+/// use tis_100::parse::parse_program;
+///
+/// let prog = parse_program("start: mov up acc\njez start\nmov acc down\n").unwrap();
+/// let src = disassemble(&prog);
+/// assert_eq!(src, "A: MOV UP ACC\nJEZ A\nMOV ACC DOWN\n");
+/// assert_eq!(parse_program(&src).unwrap(), prog);
+/// ```
+pub fn disassemble(prog: &Program) -> String {
+    let mut targets = prog.iter().filter_map(|instruction| instruction.jump_target()).collect::<Vec<_>>();
+    targets.sort();
+    targets.dedup();
+
+    let mut src = String::new();
+
+    for (index, instruction) in prog.iter().enumerate() {
+        let text = instruction.to_string();
+
+        if targets.contains(&(index as isize)) {
+            let label = jump_label(index as isize);
+
+            // The game ignores anything past the character limit, so a label that doesn't fit
+            // goes on its own line.
+            if label.len() + 2 + text.len() <= NUM_CHARS {
+                src.push_str(&format!("{}: {}\n", label, text));
+                continue;
+            }
+
+            src.push_str(&format!("{}:\n", label));
+        }
+
+        src.push_str(&text);
+        src.push('\n');
+    }
+
+    // Jumps past the last instruction refer to a label at the end of the program.
+    for &target in targets.iter().filter(|&&target| target >= prog.len() as isize) {
+        src.push_str(&format!("{}:\n", jump_label(target)));
+    }
+
+    src
+}
+
+#[test]
+fn test_disassemble() {
+    use core::Instruction::*;
+    use core::Source::*;
+    use core::Register::*;
+    use core::IoRegister::*;
+    use core::Port::*;
+    use parse::parse_program;
+    use lex::NUM_LINES;
+
+    let src = "JMP END\nL: MOV -999 RIGHT\nJRO LAST\nLP: MOV LEFT RIGHT\nJNZ LP\nJGZ L\nEND:\n";
+    let prog = parse_program(src).unwrap();
+    let disassembled = disassemble(&prog);
+
+    assert_eq!(disassembled, "JMP G\nB: MOV -999 RIGHT\nJRO LAST\nD: MOV LEFT RIGHT\nJNZ D\nJGZ B\nG:\n");
+    assert_eq!(parse_program(&disassembled).unwrap(), prog);
+
+    // Labels that don't fit on the line of their instruction are moved to the line before.
+    let prog = vec![Mov(VAL(-99999), IO(DIR(RIGHT))), Jmp(0)];
+    assert_eq!(disassemble(&prog), "A:\nMOV -99999 RIGHT\nJMP A\n");
+    assert_eq!(parse_program(&disassemble(&prog)).unwrap(), prog);
+
+    assert_eq!(disassemble(&Vec::new()), "");
+
+    // A full program that needs a line for a label doesn't fit in the lines that the lexer reads.
+    let mut prog = vec![Mov(VAL(-99999), IO(DIR(RIGHT))), Jmp(0)];
+    prog.resize(NUM_LINES, Nop);
+    let disassembled = disassemble(&prog);
+    assert_eq!(disassembled.lines().count(), NUM_LINES + 1);
+    assert!(parse_program(&disassembled).unwrap() != prog);
+}
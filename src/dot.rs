@@ -0,0 +1,240 @@
+//! Export of TIS-100 systems as Graphviz DOT graphs.
+//!
+//! A `Graph` shows every tile of a configured `Tis100`, labeled with its kind and its program, and
+//! every connection on the bus, including the one-way connections from the test inputs and to the
+//! test outputs. When the graph is given a `Profile` of a run, each connection is labeled with the
+//! number of values that crossed it, and connections that were never used are drawn dashed.
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Error};
+use vec_map::VecMap;
+use io::NodeId;
+use machine::{Tis100, NUM_NODES, INPUT_0, OUTPUT_0};
+use profile::Profile;
+use save::split_nodes;
+use spec::{Stream, StreamKind, Tile};
+
+/// The number of nodes in each row of the TIS-100.
+const ROW_LENGTH: usize = 4;
+
+/// A DOT graph of a configured `Tis100`.
+///
+/// # Example
+///
+/// ```
+/// use tis_100::dot::Graph;
+/// use tis_100::machine::Tis100;
+/// use tis_100::save::{Save, parse_save};
+/// use tis_100::spec::{SpecBuilder, PuzzleSpec};
+///
+/// let src = "@1\nMOV UP DOWN\n";
+/// let mut spec = SpecBuilder::new()
+///     .input("IN.A", 1, vec![1, 2, 3])
+///     .output("OUT.A", 1, vec![1, 2, 3])
+///     .build(parse_save(src).unwrap()).ok().unwrap();
+///
+/// let mut cpu = Tis100::new();
+/// spec.setup(&mut cpu).ok().unwrap();
+///
+/// let layout = spec.layout();
+/// let dot = Graph::new(&cpu, &layout, spec.test_streams()).source(src).to_string();
+/// assert!(dot.starts_with("digraph tis100 {"));
+/// assert!(dot.contains("n1 -> n5 [label=\"DOWN\"]"));
+/// assert!(dot.contains("in1 -> n1 [label=\"DOWN\"]"));
+/// ```
+pub struct Graph<'a> {
+    cpu: &'a Tis100,
+    layout: &'a [Tile],
+    streams: &'a [Stream],
+    programs: VecMap<String>,
+    values: Option<HashMap<(NodeId, NodeId), usize>>,
+}
+
+impl<'a> Graph<'a> {
+    /// Construct a new `Graph` of a `Tis100` that was configured with the given layout and
+    /// streams.
+    pub fn new(cpu: &'a Tis100, layout: &'a [Tile], streams: &'a [Stream]) -> Graph<'a> {
+        Graph {
+            cpu: cpu,
+            layout: layout,
+            streams: streams,
+            programs: VecMap::new(),
+            values: None,
+        }
+    }
+
+    /// Label the compute nodes with their source from a save file, including labels and comments.
+    /// Without the source, compute nodes are labeled with the size of their program.
+    pub fn source(mut self, src: &str) -> Graph<'a> {
+        for (node, _, node_src) in split_nodes(src) {
+            let lines = node_src.lines()
+                .map(|line| line.trim_end())
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<_>>();
+            self.programs.insert(node, lines.join("\n"));
+        }
+        self
+    }
+
+    /// Label the connections with the number of values that crossed them during a profiled run.
+    pub fn profile(mut self, profile: &Profile) -> Graph<'a> {
+        self.values = Some(profile.edges.iter().map(|edge| ((edge.from, edge.to), edge.values)).collect());
+        self
+    }
+
+    /// Get the name of the node that a stream is attached to in the graph.
+    fn stream_node(&self, stream: &Stream) -> (NodeId, String) {
+        let input = match stream.kind {
+            StreamKind::Input => true,
+            StreamKind::Custom(_) => self.cpu.has_node(INPUT_0 + stream.node),
+            _ => false,
+        };
+
+        if input {
+            (INPUT_0 + stream.node, format!("in{}", stream.node))
+        } else {
+            (OUTPUT_0 + stream.node, format!("out{}", stream.node))
+        }
+    }
+
+    /// Get the label of a tile.
+    fn tile_label(&self, node: NodeId, tile: &Tile) -> String {
+        let kind = match *tile {
+            Tile::Compute => "COMPUTE".to_string(),
+            Tile::Memory => "STACK MEMORY".to_string(),
+            Tile::Queue => "QUEUE MEMORY".to_string(),
+            Tile::Ram => "RANDOM ACCESS MEMORY".to_string(),
+            Tile::Damaged => "DAMAGED".to_string(),
+            Tile::Custom(ref name) => name.to_uppercase(),
+        };
+
+        let mut label = format!("NODE {}: {}", node, kind);
+
+        if *tile == Tile::Compute {
+            let size = self.cpu.program(node).map_or(0, |prog| prog.len());
+            match self.programs.get(node) {
+                Some(src) if size > 0 => label = format!("{}\n\n{}", label, src),
+                _ if size > 0 => label = format!("{}\n{} INSTRUCTIONS", label, size),
+                _ => (),
+            }
+        }
+
+        label
+    }
+}
+
+impl<'a> Display for Graph<'a> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        writeln!(f, "digraph tis100 {{")?;
+        writeln!(f, "    node [shape=box, fontname=\"monospace\"];")?;
+
+        let mut names: VecMap<String> = VecMap::new();
+
+        for (node, tile) in self.layout.iter().enumerate().take(NUM_NODES) {
+            let name = format!("n{}", node);
+            let style = match *tile {
+                Tile::Compute if self.cpu.program(node).map_or(true, |prog| prog.is_empty()) => ", style=dotted",
+                Tile::Compute => "",
+                Tile::Memory | Tile::Queue | Tile::Ram => ", shape=cylinder",
+                Tile::Damaged => ", style=filled, fillcolor=gray",
+                Tile::Custom(_) => ", shape=box3d",
+            };
+
+            writeln!(f, "    {} [label=\"{}\"{}];", name, escape(&self.tile_label(node, tile)), style)?;
+            names.insert(node, name);
+        }
+
+        for stream in self.streams.iter() {
+            let (node, name) = self.stream_node(stream);
+            writeln!(f, "    {} [label=\"{}\", shape=ellipse];", name, escape(&stream.name))?;
+            names.insert(node, name);
+        }
+
+        // Keep the nodes in the same grid as the game, with the inputs above and the outputs below.
+        let rows = (self.layout.len().min(NUM_NODES) + ROW_LENGTH - 1) / ROW_LENGTH;
+        for row in 0..rows {
+            let nodes = (row * ROW_LENGTH..(row + 1) * ROW_LENGTH)
+                .filter_map(|node| names.get(node).cloned())
+                .collect::<Vec<_>>();
+            writeln!(f, "    {{ rank=same; {}; }}", nodes.join("; "))?;
+        }
+
+        for &(first, rank) in [(INPUT_0, "min"), (OUTPUT_0, "max")].iter() {
+            let nodes = (first..first + ROW_LENGTH)
+                .filter_map(|node| names.get(node).cloned())
+                .collect::<Vec<_>>();
+            if !nodes.is_empty() {
+                writeln!(f, "    {{ rank={}; {}; }}", rank, nodes.join("; "))?;
+            }
+        }
+
+        for (from, port, to) in self.cpu.bus().connections() {
+            let (from_name, to_name) = match (names.get(from), names.get(to)) {
+                (Some(from_name), Some(to_name)) => (from_name, to_name),
+                _ => continue,
+            };
+
+            match self.values.as_ref().map(|values| values.get(&(from, to)).cloned().unwrap_or(0)) {
+                Some(0) => writeln!(f, "    {} -> {} [label=\"{:?}\", style=dashed, color=gray];", from_name, to_name, port)?,
+                Some(values) => writeln!(f, "    {} -> {} [label=\"{:?}: {}\", penwidth={}];", from_name, to_name, port,
+                                         values, pen_width(values))?,
+                None => writeln!(f, "    {} -> {} [label=\"{:?}\"];", from_name, to_name, port)?,
+            }
+        }
+
+        writeln!(f, "}}")
+    }
+}
+
+/// Get the width of a connection that carried the given number of values.
+fn pen_width(values: usize) -> usize {
+    1 + (values as f64).log10().floor() as usize
+}
+
+/// Escape a string for use in a DOT label. Lines are left-justified.
+fn escape(label: &str) -> String {
+    let mut escaped = String::new();
+    for line in label.lines() {
+        escaped.push_str(&line.replace('\\', "\\\\").replace('"', "\\\""));
+        escaped.push_str("\\l");
+    }
+    escaped
+}
+
+#[test]
+fn test_graph() {
+    use machine::Puzzle;
+    use save::{Save, parse_save};
+    use spec::{SpecBuilder, PuzzleSpec};
+
+    let src = "@1\nMOV UP DOWN # \"pass\"\n@5\nMOV UP DOWN\n@9\nMOV UP DOWN\n";
+    let spec = SpecBuilder::new()
+        .tile(3, Tile::Damaged)
+        .tile(4, Tile::Memory)
+        .input("IN.A", 1, vec![1, 2, 3])
+        .output("OUT.A", 1, vec![1, 2, 3])
+        .build(Save::new()).ok().unwrap();
+
+    let mut puzzle = Puzzle::new(&spec, &parse_save(src).unwrap(), 0).ok().unwrap();
+    puzzle.set_profiling(true);
+    puzzle.run();
+
+    let layout = spec.layout();
+    let streams = spec.streams(0).ok().unwrap();
+    let profile = puzzle.profile().unwrap();
+    let dot = Graph::new(puzzle.cpu(), &layout, &streams).source(src).profile(&profile).to_string();
+
+    assert!(dot.contains("n1 [label=\"NODE 1: COMPUTE\\l\\lMOV UP DOWN # \\\"pass\\\"\\l\"];"));
+    assert!(dot.contains("n0 [label=\"NODE 0: COMPUTE\\l\", style=dotted];"));
+    assert!(dot.contains("n3 [label=\"NODE 3: DAMAGED\\l\", style=filled, fillcolor=gray];"));
+    assert!(dot.contains("n4 [label=\"NODE 4: STACK MEMORY\\l\", shape=cylinder];"));
+    assert!(dot.contains("in1 [label=\"IN.A\\l\", shape=ellipse];"));
+    assert!(dot.contains("n9 -> out1 [label=\"DOWN: 3\", penwidth=1];"));
+    assert!(dot.contains("n5 -> n1 [label=\"UP\", style=dashed, color=gray];"));
+    assert!(dot.contains("{ rank=same; n0; n1; n2; n3; }"));
+    assert!(dot.contains("{ rank=max; out1; }"));
+
+    // Unused inputs and outputs are left out.
+    assert!(!dot.contains("in0"));
+    assert!(!dot.contains("-> out0"));
+}
@@ -0,0 +1,15 @@
+//! Puzzles shared by the tests of several modules.
+
+use save::{Save, parse_save};
+use spec::{Spec, SpecBuilder};
+
+/// Get a spec that passes 1, 2 and 3 from IN.A to OUT.A, and a save that passes them through
+/// nodes 1, 5 and 9.
+pub fn pipeline() -> (Spec, Save) {
+    let spec = SpecBuilder::new()
+        .input("IN.A", 1, vec![1, 2, 3])
+        .output("OUT.A", 1, vec![1, 2, 3])
+        .build(Save::new()).ok().unwrap();
+
+    (spec, parse_save("@1\nMOV UP DOWN\n@5\nMOV UP DOWN\n@9\nMOV UP DOWN\n").unwrap())
+}
@@ -1,7 +1,9 @@
 //! Constructs for generating images using the TIS-100.
 
+use std::hash::{Hash, Hasher};
+
 /// The colors that the TIS-100 can generate.
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
 pub enum Color {
     Black,
     DarkGrey,
@@ -26,7 +28,7 @@ impl Color {
 use self::Color::*;
 
 /// The operational modes of the image.
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
 enum ImageMode {
     Move,
     Paint,
@@ -81,6 +83,34 @@ impl Image {
         }
     }
 
+    /// Get the width of the image.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Get the height of the image.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Get the `(row, column)` coordinates of every pixel that is different in the other image.
+    pub fn diff(&self, other: &Image) -> Vec<(usize, usize)> {
+        self.data.iter()
+            .zip(other.data.iter())
+            .enumerate()
+            .filter(|&(_, (a, b))| a != b)
+            .map(|(i, _)| (i / self.width, i % self.width))
+            .collect()
+    }
+
+    /// Feed the image's data and its drawing position into the hasher.
+    pub fn hash_state(&self, mut state: &mut Hasher) {
+        self.data.hash(&mut state);
+        self.mode.hash(&mut state);
+        self.position.hash(&mut state);
+        self.offset.hash(&mut state);
+    }
+
     /// Retrieve the image's data.
     pub fn data(&self) -> &Vec<Color> {
         &self.data
@@ -166,3 +196,12 @@ fn test_image_write() {
 
     assert_eq!(expected, image.data().clone());
 }
+
+#[test]
+fn test_image_diff() {
+    let image = Image::with_data(&vec![1, 2, 3, 4], 2, 2);
+    let other = Image::with_data(&vec![1, 0, 3, 0], 2, 2);
+
+    assert_eq!(image.diff(&other), vec![(0, 1), (1, 1)]);
+    assert!(image.diff(&image).is_empty());
+}
@@ -3,6 +3,7 @@
 use vec_map::VecMap;
 use std::collections::HashMap;
 use std::collections::hash_map::Iter;
+use std::hash::{Hash, Hasher};
 use core::{Port, opposite_port};
 
 /// A unique identifier for a node.
@@ -15,6 +16,34 @@ pub type PortId = usize;
 #[derive(Debug)]
 pub struct Connection(PortId, NodeId);
 
+/// A value that was written or read on the bus.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Transfer {
+    /// The node that wrote the value.
+    pub from: NodeId,
+    /// The output port of the node that wrote the value.
+    pub port: Port,
+    /// The node that the value was written to.
+    pub to: NodeId,
+    /// The value that was written.
+    pub value: isize,
+}
+
+/// A one-way connection on the bus, and the values that it carries.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Link {
+    /// The node that writes to the connection.
+    pub from: NodeId,
+    /// The output port of the node that writes to the connection.
+    pub port: Port,
+    /// The node that reads from the connection.
+    pub to: NodeId,
+    /// The committed value that is waiting to be read.
+    pub value: Option<isize>,
+    /// The value that was written since the last commit, which can be read once it is committed.
+    pub pending: Option<isize>,
+}
+
 /// An `IoBus` is used to pass messages between nodes. Nodes are represented by `usize` indices.
 /// Nodes must first be connected before they can pass messages. Nodes can be connected using either
 /// half-duplex or full-duplex channels.
@@ -64,6 +93,10 @@ pub struct IoBus {
     writes: VecMap<isize>,
     write_blocks: VecMap<isize>,
     nodes: VecMap<PortMap>,
+    traffic: bool,
+    logging: bool,
+    written: Vec<Transfer>,
+    transfers: Vec<Transfer>,
 }
 
 impl IoBus {
@@ -75,6 +108,10 @@ impl IoBus {
             writes: VecMap::new(),
             write_blocks: VecMap::new(),
             nodes: VecMap::new(),
+            traffic: false,
+            logging: false,
+            written: Vec::new(),
+            transfers: Vec::new(),
         }
     }
 
@@ -136,12 +173,124 @@ impl IoBus {
         }
 
         self.writes.clear();
+        self.traffic = false;
+        self.written.clear();
+        self.transfers.clear();
+    }
+
+    /// Check if any values have been written or read since the last commit.
+    pub fn has_traffic(&self) -> bool {
+        self.traffic
+    }
+
+    /// Start or stop logging the values that are written and read. The logs are cleared on every
+    /// commit, and stay empty while logging is off.
+    pub fn set_logging(&mut self, enabled: bool) {
+        self.logging = enabled;
+    }
+
+    /// Get every value that has been written since the last commit, if logging is on. A value
+    /// that is written to more than one port is logged once for each port.
+    pub fn written(&self) -> &[Transfer] {
+        &self.written
+    }
+
+    /// Get every value that has been read since the last commit, if logging is on.
+    pub fn transfers(&self) -> &[Transfer] {
+        &self.transfers
+    }
+
+    /// Feed the values that are waiting to be read, and the nodes that are blocked on writes, into
+    /// the hasher.
+    pub fn hash_state(&self, mut state: &mut Hasher) {
+        // The lengths keep the two maps apart, so that the bytes describe a single state.
+        self.ports.len().hash(&mut state);
+        for (index, value) in self.ports.iter() {
+            (index, value).hash(&mut state);
+        }
+
+        self.write_blocks.len().hash(&mut state);
+        for (node, value) in self.write_blocks.iter() {
+            (node, value).hash(&mut state);
+        }
+    }
+
+    /// Get every one-way connection on the bus as `(from, port, to)`, where `port` is the output
+    /// port of the sending node, in order of the sending nodes.
+    pub fn connections(&self) -> Vec<(NodeId, Port, NodeId)> {
+        self.links().iter().map(|link| (link.from, link.port, link.to)).collect()
+    }
+
+    /// Get every one-way connection on the bus with its contents, in the same order as
+    /// `connections`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tis_100::core::Port::*;
+    /// use tis_100::io::IoBus;
+    ///
+    /// let mut bus = IoBus::new();
+    /// bus.connect_full(0, 1, RIGHT);
+    /// bus.view(0).write(RIGHT, 42);
+    ///
+    /// let links = bus.links();
+    /// assert_eq!((links[0].from, links[0].port, links[0].to), (0, RIGHT, 1));
+    /// assert_eq!((links[0].value, links[0].pending), (None, Some(42)));
+    /// assert_eq!((links[1].from, links[1].port, links[1].to), (1, LEFT, 0));
+    ///
+    /// bus.commit();
+    /// assert_eq!(bus.link(0, RIGHT).map(|link| (link.value, link.pending)), Some((Some(42), None)));
+    /// ```
+    pub fn links(&self) -> Vec<Link> {
+        let mut links = self.nodes.iter()
+            .flat_map(|(from, map)| map.output_iter().map(move |(&port, connection)| (from, port, connection)))
+            .map(|(from, port, connection)| self.make_link(from, port, connection))
+            .collect::<Vec<_>>();
+        links.sort_by_key(|link| (link.from, link.to, link.port as usize));
+        links
+    }
+
+    /// Get the connection from an output port of a node with its contents, if the port is
+    /// connected.
+    pub fn link(&self, node: NodeId, port: Port) -> Option<Link> {
+        self.get_output(node, port).map(|connection| self.make_link(node, port, connection))
+    }
+
+    /// Get the committed value on an output port of a node that hasn't been read yet.
+    pub fn value(&self, node: NodeId, port: Port) -> Option<isize> {
+        self.get_output(node, port).and_then(|&Connection(index, _)| self.ports.get(index).cloned())
+    }
+
+    /// Get the value that was written to an output port of a node since the last commit.
+    pub fn pending(&self, node: NodeId, port: Port) -> Option<isize> {
+        self.get_output(node, port).and_then(|&Connection(index, _)| self.writes.get(index).cloned())
+    }
+
+    /// Check if a node is blocked until a value that it wrote has been read.
+    pub fn is_blocked(&self, node: NodeId) -> bool {
+        self.write_blocks.get(node).is_some()
+    }
+
+    /// Get the node that sends values to the given input port of a node.
+    pub fn input_peer(&self, node: NodeId, port: Port) -> Option<NodeId> {
+        self.get_input(node, port).map(|&Connection(_, peer)| peer)
+    }
+
+    /// Get the node that receives values from the given output port of a node.
+    pub fn output_peer(&self, node: NodeId, port: Port) -> Option<NodeId> {
+        self.get_output(node, port).map(|&Connection(_, peer)| peer)
     }
 
     /// Send data on a given port for a node.
     fn write(&mut self, node: NodeId, port: Port, value: isize) {
-        if let Some(&Connection(index, _)) = self.get_output(node, port) {
+        if let Some(&Connection(index, to)) = self.get_output(node, port) {
             self.writes.insert(index, value);
+            self.traffic = true;
+
+            if self.logging {
+                self.written.push(Transfer { from: node, port: port, to: to, value: value });
+            }
 
             // Writing to the IoBus causes a node to block until the value has been consumed by a
             // read.
@@ -149,9 +298,16 @@ impl IoBus {
         }
     }
 
-    /// Check if an output port has been read for a node.
-    fn is_blocked(&self, node: NodeId) -> bool {
-        self.write_blocks.get(node).is_some()
+    /// Withdraw all of the values that a node has written which haven't been read yet.
+    fn retract(&mut self, node: NodeId) {
+        if let Some(map) = self.nodes.get(node) {
+            for (_, &Connection(index, _)) in map.output_iter() {
+                self.writes.remove(index);
+            }
+        }
+
+        self.clear_outputs(node);
+        self.write_blocks.remove(node);
     }
 
     /// Receive data on a given port for a node. Whenever a node reads from an input, all of the
@@ -159,6 +315,10 @@ impl IoBus {
     fn read(&mut self, node: NodeId, port: Port) -> Option<isize> {
         if let Some(&Connection(index, out_node)) = self.get_input(node, port) {
             if let Some(val) = self.ports.remove(index) {
+                self.traffic = true;
+                if self.logging {
+                    self.transfers.push(Transfer { from: out_node, port: opposite_port(port), to: node, value: val });
+                }
                 self.clear_outputs(out_node);
                 self.write_blocks.remove(out_node);
                 return Some(val);
@@ -186,6 +346,18 @@ impl IoBus {
         }
     }
 
+    /// Describe an output connection of a node and its contents.
+    fn make_link(&self, from: NodeId, port: Port, connection: &Connection) -> Link {
+        let Connection(index, to) = *connection;
+        Link {
+            from: from,
+            port: port,
+            to: to,
+            value: self.ports.get(index).cloned(),
+            pending: self.writes.get(index).cloned(),
+        }
+    }
+
     /// Create a new `PortMap`.
     fn insert_map(&mut self, node: NodeId) {
         self.nodes.insert(node, PortMap::new());
@@ -242,6 +414,11 @@ impl<'a> IoBusView<'a> {
     pub fn is_blocked(&self) -> bool {
         self.bus.is_blocked(self.node)
     }
+
+    /// Withdraw any values that have been written but not read yet.
+    pub fn retract(&mut self) {
+        self.bus.retract(self.node);
+    }
 }
 
 /// For a given node, this maps from an input or output port direction to the bus index containing
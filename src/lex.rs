@@ -1,19 +1,43 @@
 //! Functions for splitting TIS-100 assembly code into lexemes.
 
 /// The maximum number of characters per line.
-const NUM_CHARS: usize = 18;
+pub const NUM_CHARS: usize = 18;
+
+/// The maximum number of lines per program that are lexed. The game allows one line less, which
+/// is `strict::GAME_LINES`.
+pub const NUM_LINES: usize = 16;
+
+/// A range of characters on a source line, from `start` up to but not including `end`. Columns
+/// count characters from the start of the line, and byte offsets count bytes, both starting
+/// from 0.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub byte_start: usize,
+    pub byte_end: usize,
+}
 
-/// The maximum number of lines per program.
-const NUM_LINES: usize = 16;
+impl Span {
+    /// Get the span from the start of this span to the end of another span.
+    pub fn to(&self, other: Span) -> Span {
+        Span {
+            start: self.start,
+            end: other.end,
+            byte_start: self.byte_start,
+            byte_end: other.byte_end,
+        }
+    }
+}
 
-/// A label and the index of the instruction that it refers to.
+/// A label, the index of the instruction that it refers to, and the span of its name.
 #[derive(Debug, PartialEq)]
-pub struct Label(pub String, pub usize);
+pub struct Label(pub String, pub usize, pub Span);
 
 /// A lexed source line, consisting of its line number, an optional label,
-/// and one or more lexemes that form an instruction.
+/// zero or more lexemes that form an instruction, and the span of each lexeme.
 #[derive(Debug, PartialEq)]
-pub struct Line(pub usize, pub Option<Label>, pub Vec<String>);
+pub struct Line(pub usize, pub Option<Label>, pub Vec<String>, pub Vec<Span>);
 
 /// Split the source code into lines of labels and lexemes.
 pub fn lex_program(src: &str) -> Vec<Line> {
@@ -21,9 +45,9 @@ pub fn lex_program(src: &str) -> Vec<Line> {
     let mut lines = Vec::new();
 
     for (index, line) in src.lines().take(NUM_LINES).enumerate() {
-        let (maybe_label, words) = lex_line(line);
-        let label = if let Some(label) = maybe_label {
-            Some(Label(label, next_op))
+        let (maybe_label, label_span, words, spans) = lex_line(line);
+        let label = if let (Some(label), Some(span)) = (maybe_label, label_span) {
+            Some(Label(label, next_op, span))
         } else {
             None
         };
@@ -32,29 +56,45 @@ pub fn lex_program(src: &str) -> Vec<Line> {
             next_op += 1;
         }
 
-        lines.push(Line(index, label, words));
+        lines.push(Line(index, label, words, spans));
     }
 
     lines
 }
 
-/// Lex a single line of source code.
-fn lex_line(line: &str) -> (Option<String>, Vec<String>) {
+/// Lex a single line of source code into its label and lexemes, along with their spans. An empty
+/// label is spanned by its `:`.
+fn lex_line(line: &str) -> (Option<String>, Option<Span>, Vec<String>, Vec<Span>) {
     let mut label = None;
+    let mut label_span = None;
     let mut words = Vec::new();
+    let mut spans = Vec::new();
     let mut word = String::new();
 
-    for c in line.to_uppercase().chars().take(NUM_CHARS) {
+    // The column and byte offset of the first character of the current word, and of the character
+    // after its last one.
+    let mut start = (0, 0);
+    let mut end = (0, 0);
+
+    for (column, (byte, c)) in line.char_indices().take(NUM_CHARS).enumerate() {
+        let next = (column + 1, byte + c.len_utf8());
+
         if is_comment_delimiter(c) {
             break;
         } else if is_whitespace(c) {
             if word.len() > 0 {
                 words.push(word.clone());
+                spans.push(span(start, end));
                 word.clear();
             }
         } else if label.is_some() || !is_label_delimiter(c) {
-            word.push(c)
+            if word.is_empty() {
+                start = (column, byte);
+            }
+            word.extend(c.to_uppercase());
+            end = next;
         } else {
+            label_span = Some(if word.is_empty() { span((column, byte), next) } else { span(start, end) });
             label = Some(word.clone());
             word.clear();
         }
@@ -62,9 +102,20 @@ fn lex_line(line: &str) -> (Option<String>, Vec<String>) {
 
     if word.len() > 0 {
         words.push(word.clone());
+        spans.push(span(start, end));
     }
 
-    (label, words)
+    (label, label_span, words, spans)
+}
+
+/// Construct a `Span` from the column and byte offset of its start and end.
+fn span(start: (usize, usize), end: (usize, usize)) -> Span {
+    Span {
+        start: start.0,
+        end: end.0,
+        byte_start: start.1,
+        byte_end: end.1,
+    }
 }
 
 /// Check if a character is whitespace.
@@ -106,20 +157,20 @@ fn test_is_label_delimiter() {
 
 #[test]
 fn test_lex_line() {
-    let (lbl, lex) = lex_line("LABEL: MOV UP ACC # comment");
+    let (lbl, _, lex, _) = lex_line("LABEL: MOV UP ACC # comment");
     assert_eq!(lbl, Some("LABEL".to_string()));
     assert_eq!(lex.len(), 3);
     assert_eq!(lex[0], "MOV");
     assert_eq!(lex[1], "UP");
     assert_eq!(lex[2], "ACC");
 
-    let (lbl, lex) = lex_line("ADD 1");
+    let (lbl, _, lex, _) = lex_line("ADD 1");
     assert_eq!(lbl, None);
     assert_eq!(lex.len(), 2);
     assert_eq!(lex[0], "ADD");
     assert_eq!(lex[1], "1");
 
-    let (lbl, lex) = lex_line(":ADD 1 2 3");
+    let (lbl, _, lex, _) = lex_line(":ADD 1 2 3");
     assert_eq!(lbl, Some("".to_string()));
     assert_eq!(lex.len(), 4);
     assert_eq!(lex[0], "ADD");
@@ -127,17 +178,17 @@ fn test_lex_line() {
     assert_eq!(lex[2], "2");
     assert_eq!(lex[3], "3");
 
-    let (lbl, lex) = lex_line(",,LABEL:,,ADD,1,,,,,");
+    let (lbl, _, lex, _) = lex_line(",,LABEL:,,ADD,1,,,,,");
     assert_eq!(lbl, Some("LABEL".to_string()));
     assert_eq!(lex.len(), 2);
     assert_eq!(lex[0], "ADD");
     assert_eq!(lex[1], "1");
 
-    let (lbl, lex) = lex_line("# LABEL: MOV UP ACC");
+    let (lbl, _, lex, _) = lex_line("# LABEL: MOV UP ACC");
     assert_eq!(lbl, None);
     assert_eq!(lex.len(), 0);
 
-    let (lbl, lex) = lex_line("LABEL: MOV LEFT RIGHT");
+    let (lbl, _, lex, _) = lex_line("LABEL: MOV LEFT RIGHT");
     assert_eq!(lbl, Some("LABEL".to_string()));
     assert_eq!(lex.len(), 3);
     assert_eq!(lex[0], "MOV");
@@ -155,9 +206,28 @@ fn test_lex_program() {
 
     let lines = lex_program("1:\n2:\n3: ADD 1\n4: ADD 1\n");
     assert_eq!(lines.len(), 4);
-    assert_eq!(lines[0].1, Some(Label("1".to_string(), 0)));
-    assert_eq!(lines[1].1, Some(Label("2".to_string(), 0)));
-    assert_eq!(lines[2].1, Some(Label("3".to_string(), 0)));
-    assert_eq!(lines[3].1, Some(Label("4".to_string(), 1)));
+    let labels = lines.iter()
+        .map(|line| line.1.as_ref().map(|&Label(ref name, index, _)| (name.as_str(), index)))
+        .collect::<Vec<_>>();
+    assert_eq!(labels, vec![Some(("1", 0)), Some(("2", 0)), Some(("3", 0)), Some(("4", 1))]);
+}
+
+#[test]
+fn test_lex_spans() {
+    let (lbl, lbl_span, lex, spans) = lex_line("  loop: mov é,acc # x");
+    assert_eq!(lbl, Some("LOOP".to_string()));
+    assert_eq!(lbl_span, Some(span((2, 2), (6, 6))));
+    assert_eq!(lex, vec!["MOV", "É", "ACC"]);
+    assert_eq!(spans, vec![span((8, 8), (11, 11)), span((12, 12), (13, 14)), span((14, 15), (17, 18))]);
+    assert_eq!(spans[0].to(spans[2]), span((8, 8), (17, 18)));
+
+    let (_, lbl_span, _, _) = lex_line(" : NOP");
+    assert_eq!(lbl_span, Some(span((1, 1), (2, 2))));
+
+    // The line is cut to NUM_CHARS characters as it was written, before it is uppercased, so a
+    // character whose uppercase is longer doesn't push the end of the line past the limit.
+    let (lbl, _, lex, _) = lex_line("ßßß: MOV UP DOWN");
+    assert_eq!(lbl, Some("SSSSSS".to_string()));
+    assert_eq!(lex, vec!["MOV", "UP", "DOWN"]);
 }
 
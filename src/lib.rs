@@ -22,14 +22,35 @@
 //! ```
 
 extern crate hlua;
+extern crate lua52_sys;
+extern crate libc;
 extern crate vec_map;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate toml;
+extern crate getopts;
 
 pub mod core;
 pub mod lex;
 pub mod parse;
+pub mod ast;
+pub mod diagnostic;
+pub mod disasm;
 pub mod io;
 pub mod node;
 pub mod image;
 pub mod save;
+pub mod strict;
 pub mod spec;
 pub mod machine;
+pub mod deadlock;
+pub mod profile;
+pub mod coverage;
+pub mod vcd;
+pub mod dot;
+pub mod cli;
+
+#[cfg(test)]
+mod fixtures;
@@ -1,12 +1,21 @@
 //! TIS-100 emulator implementations.
 
+use std::fmt::{Display, Formatter, Error};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 use vec_map::VecMap;
+use core::{Program, Instruction};
 use core::Port::*;
-use io::IoBus;
-use node::{Node, TestNode, TestState, BasicExecutionNode};
+use io::{IoBus, NodeId, Transfer};
+use node::{Node, TestNode, TestState, TestReport, Registers, IoAction, Mode, BasicExecutionNode};
 use node::TestState::*;
 use save::Save;
-use spec::Spec;
+use spec::{self, Spec, PuzzleSpec, SpecError, Registry, Stream};
+use deadlock::{DeadlockReport, Wait, PeerState};
+use profile::{Profile, Profiler};
+use coverage::Coverage;
 
 pub const NUM_NODES: usize = 12;
 
@@ -22,6 +31,20 @@ pub const OUTPUT_1: usize = 17;
 pub const OUTPUT_2: usize = 18;
 pub const OUTPUT_3: usize = 19;
 
+/// Get the name of a node, as used in reports: `NODE n` for execution nodes, and `INPUT n` or
+/// `OUTPUT n` for the nodes that are attached to the test streams.
+pub fn node_name(node: NodeId) -> String {
+    if node < NUM_NODES {
+        format!("NODE {}", node)
+    } else if node >= INPUT_0 && node < INPUT_0 + NUM_INPUTS {
+        format!("INPUT {}", node - INPUT_0)
+    } else if node >= OUTPUT_0 && node < OUTPUT_0 + NUM_OUTPUTS {
+        format!("OUTPUT {}", node - OUTPUT_0)
+    } else {
+        format!("{}", node)
+    }
+}
+
 /// Implements the *Simple Sandbox* puzzle from the game.
 ///
 /// # Example
@@ -84,27 +107,263 @@ impl Sandbox {
     pub fn read_console(&mut self) -> Option<isize> {
         self.cpu.read_output(2)
     }
+
+    /// Add an observer that is told about the events of every cycle. See `Tis100::add_observer`.
+    pub fn add_observer(&mut self, observer: Rc<RefCell<Observer>>) {
+        self.cpu.add_observer(observer);
+    }
+}
+
+/// The number of cycles that a `Puzzle` may run for by default before it times out.
+pub const DEFAULT_MAX_CYCLES: usize = 100_000;
+
+/// The result of running a `Puzzle` until it stops.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize)]
+pub enum Outcome {
+    /// Every test output received all of its expected values.
+    #[serde(rename = "pass")]
+    Passed,
+    /// A test output received a wrong value, or more values than expected.
+    #[serde(rename = "fail")]
+    Failed,
+    /// Every node is stalled, and at least one is waiting to read or write a value that will
+    /// never arrive.
+    #[serde(rename = "deadlock")]
+    Deadlock,
+    /// The puzzle reached its cycle limit before it passed or failed.
+    #[serde(rename = "timeout")]
+    Timeout,
+    /// Every node is stalled, and none of them have anything left to execute.
+    #[serde(rename = "halt")]
+    Halt,
+    /// The system returned to a state that it was in before without finishing the tests, so it
+    /// will repeat the same cycles forever. Only reported when livelock detection is enabled.
+    #[serde(rename = "livelock")]
+    Livelock,
+}
+
+/// The results of a single test stream in a `PuzzleReport`.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct StreamReport {
+    /// The name of the stream.
+    pub name: String,
+    /// The output that the stream is attached to.
+    pub node: usize,
+    /// The state of the stream's test.
+    pub state: TestState,
+    /// The values or pixels that the test received, if the test can describe them.
+    pub details: Option<TestReport>,
+}
+
+/// A summary of a `Puzzle`, similar to the output panel in the game. It lists the expected and
+/// received values for every test stream, and shows where the first mismatch happened.
+///
+/// # Example
+///
+/// ```
+/// use tis_100::save::{Save, parse_save};
+/// use tis_100::spec::SpecBuilder;
+/// use tis_100::machine::{Puzzle, Outcome};
+/// use tis_100::node::TestReport;
+///
+/// let spec = SpecBuilder::new()
+///     .input("IN.A", 1, vec![1, 2, 3])
+///     .output("OUT.A", 1, vec![1, 2, 3])
+///     .build(Save::new()).ok().unwrap();
+///
+/// let save = parse_save("@1\nMOV UP DOWN\n@5\nMOV UP DOWN\n@9\nMOV UP ACC\nMOV ACC DOWN\nMOV 0 DOWN\n").unwrap();
+/// let mut puzzle = Puzzle::new(&spec, &save, 0).ok().unwrap();
+/// puzzle.run();
+///
+/// let report = puzzle.report();
+/// assert_eq!(report.outcome, Some(Outcome::Failed));
+///
+/// match report.streams[0].details {
+///     Some(TestReport::Output { mismatch, .. }) => assert_eq!(mismatch, Some(1)),
+///     _ => panic!("expected an output report"),
+/// }
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct PuzzleReport {
+    /// The outcome of the puzzle, or `None` if it is still running.
+    pub outcome: Option<Outcome>,
+    /// The number of cycles that the puzzle has run for.
+    pub cycles: usize,
+    /// The results of each test stream, in the order of their outputs.
+    pub streams: Vec<StreamReport>,
+    /// The wait-for graph of the nodes, if the puzzle is deadlocked.
+    pub deadlock: Option<DeadlockReport>,
+    /// The number of cycles after which the system repeats itself, if the puzzle is livelocked.
+    pub livelock_period: Option<usize>,
+}
+
+impl Display for PuzzleReport {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        let outcome = match self.outcome {
+            Some(Outcome::Passed) => "PASSED",
+            Some(Outcome::Failed) => "FAILED",
+            Some(Outcome::Deadlock) => "DEADLOCK",
+            Some(Outcome::Timeout) => "TIMEOUT",
+            Some(Outcome::Halt) => "HALTED",
+            Some(Outcome::Livelock) => "LIVELOCK",
+            None => "RUNNING",
+        };
+
+        writeln!(f, "{} AFTER {} CYCLES", outcome, self.cycles)?;
+
+        for stream in self.streams.iter() {
+            let state = match stream.state {
+                Testing => "INCOMPLETE",
+                Passed => "PASSED",
+                Failed => "FAILED",
+            };
+
+            writeln!(f)?;
+            writeln!(f, "{} (OUTPUT {}): {}", stream.name, stream.node, state)?;
+
+            match stream.details {
+                Some(TestReport::Output { ref expected, ref received, mismatch }) => {
+                    writeln!(f, "{:>5} {:>9} {:>9}", "#", "EXPECTED", "RECEIVED")?;
+
+                    for i in 0..expected.len().max(received.len()) {
+                        let cell = |values: &Vec<isize>| match values.get(i) {
+                            Some(val) => val.to_string(),
+                            None => "-".to_string(),
+                        };
+                        let marker = if mismatch == Some(i) { "  <- FIRST MISMATCH" } else { "" };
+
+                        writeln!(f, "{:>5} {:>9} {:>9}{}", i, cell(expected), cell(received), marker)?;
+                    }
+                },
+                Some(TestReport::Image { width, height, ref mismatches }) => {
+                    writeln!(f, "{} OF {} PIXELS DO NOT MATCH", mismatches.len(), width * height)?;
+
+                    for &(row, col) in mismatches.iter() {
+                        writeln!(f, "  ROW {}, COLUMN {}", row, col)?;
+                    }
+                },
+                None => (),
+            }
+        }
+
+        if let Some(ref deadlock) = self.deadlock {
+            writeln!(f)?;
+            write!(f, "{}", deadlock)?;
+        }
+
+        if let Some(period) = self.livelock_period {
+            writeln!(f)?;
+            writeln!(f, "THE SYSTEM REPEATS ITSELF EVERY {} CYCLES", period)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A `Hasher` that keeps the bytes that it is given instead of hashing them, so that two states of
+/// a puzzle can be compared exactly.
+#[derive(Debug, PartialEq, Eq, Default)]
+struct Snapshot(Vec<u8>);
+
+impl Hasher for Snapshot {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.extend_from_slice(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        hasher.write(&self.0);
+        hasher.finish()
+    }
+}
+
+/// Finds the first repeated state of a puzzle using Brent's algorithm. Only one snapshot is saved,
+/// and it is replaced by the current state whenever the distance between them reaches the next
+/// power of two. Memory use doesn't grow with the number of cycles.
+struct CycleDetector {
+    saved: Option<Snapshot>,
+    cycle: usize,
+    power: usize,
+}
+
+impl CycleDetector {
+    /// Construct a new `CycleDetector` with no saved state.
+    fn new() -> CycleDetector {
+        CycleDetector {
+            saved: None,
+            cycle: 0,
+            power: 1,
+        }
+    }
+
+    /// Compare the state after the given cycle with the saved state. Returns the period of the
+    /// repetition if they are the same.
+    fn check(&mut self, snapshot: Snapshot, cycle: usize) -> Option<usize> {
+        let distance = cycle - self.cycle;
+
+        match self.saved {
+            Some(ref saved) if *saved == snapshot => return Some(distance),
+            Some(_) if distance < self.power => return None,
+            Some(_) => self.power *= 2,
+            None => (),
+        }
+
+        self.saved = Some(snapshot);
+        self.cycle = cycle;
+        None
+    }
 }
 
 /// Executes arbitrary puzzles using a spec file.
 pub struct Puzzle {
     cpu: Tis100,
     tests: VecMap<Box<TestNode>>,
+    names: VecMap<String>,
     cycles: usize,
+    max_cycles: Option<usize>,
+    detector: Option<CycleDetector>,
+    livelock_period: Option<usize>,
 }
 
 impl Puzzle {
-    pub fn from_spec(spec: &mut Spec) -> Puzzle {
+    /// Construct a new `Puzzle` from any `PuzzleSpec`, using the given seed to generate the test
+    /// streams.
+    pub fn new<S: PuzzleSpec + ?Sized>(spec: &S, save: &Save, seed: u32) -> Result<Puzzle, SpecError> {
+        let streams = spec.streams(seed)?;
+        let empty = Registry::new();
+        let registry = spec.registry().unwrap_or(&empty);
+
         let mut cpu = Tis100::new();
-        spec.setup(&mut cpu);
+        spec::setup(&spec.layout(), &streams, save, registry, &mut cpu)?;
+
+        Ok(Puzzle {
+            cpu: cpu,
+            tests: spec::tests(&streams, registry),
+            names: test_names(&streams),
+            cycles: 0,
+            max_cycles: Some(DEFAULT_MAX_CYCLES),
+            detector: None,
+            livelock_period: None,
+        })
+    }
+
+    /// Construct a new `Puzzle` from a `Spec`, using the streams that the spec has already
+    /// generated.
+    pub fn from_spec(spec: &mut Spec) -> Result<Puzzle, SpecError> {
+        let mut cpu = Tis100::new();
+        spec.setup(&mut cpu)?;
 
         let tests = spec.tests();
 
-        Puzzle {
+        Ok(Puzzle {
             cpu: cpu,
             tests: tests,
+            names: test_names(spec.test_streams()),
             cycles: 0,
-        }
+            max_cycles: Some(DEFAULT_MAX_CYCLES),
+            detector: None,
+            livelock_period: None,
+        })
     }
 
     pub fn step(&mut self) {
@@ -124,34 +383,342 @@ impl Puzzle {
         self.cpu.commit();
 
         self.cycles += 1;
+
+        if self.livelock_period.is_none() {
+            if let Some(snapshot) = self.snapshot() {
+                if let Some(ref mut detector) = self.detector {
+                    self.livelock_period = detector.check(snapshot, self.cycles);
+                }
+            }
+        }
+    }
+
+    /// Record the state of every node and the bus, or return `None` if livelock detection is
+    /// disabled or a node can't describe its state.
+    fn snapshot(&self) -> Option<Snapshot> {
+        if self.detector.is_none() {
+            return None;
+        }
+
+        let mut snapshot = Snapshot::default();
+        if !self.cpu.hash_state(&mut snapshot) {
+            return None;
+        }
+
+        for (id, node) in self.tests.iter() {
+            id.hash(&mut snapshot);
+            if !node.hash_state(&mut snapshot) {
+                return None;
+            }
+        }
+
+        Some(snapshot)
     }
 
+    /// Get the state of the tests. The puzzle fails as soon as any test fails, even if other tests
+    /// are still running.
     pub fn state(&self) -> TestState {
         let states = self.tests.iter().map(|(_, n)| n.state()).collect::<Vec<_>>();
 
-        if states.iter().any(|&s| s == Testing) {
-            Testing
-        } else if states.iter().any(|&s| s == Failed) {
+        if states.iter().any(|&s| s == Failed) {
             Failed
+        } else if states.iter().any(|&s| s == Testing) {
+            Testing
         } else {
             Passed
         }
     }
 
+    /// Get the outcome of the puzzle, or `None` if it is still running.
+    pub fn outcome(&self) -> Option<Outcome> {
+        match self.state() {
+            Passed => Some(Outcome::Passed),
+            Failed => Some(Outcome::Failed),
+            Testing => if self.is_halted() {
+                Some(Outcome::Halt)
+            } else if self.is_deadlocked() {
+                Some(Outcome::Deadlock)
+            } else if self.livelock_period.is_some() && self.cpu.stalled == 0 {
+                // A system that stops making progress also repeats its state, but it is reported
+                // as a deadlock once every node has stalled for long enough.
+                Some(Outcome::Livelock)
+            } else if self.max_cycles.map_or(false, |max| self.cycles >= max) {
+                Some(Outcome::Timeout)
+            } else {
+                None
+            },
+        }
+    }
+
+    /// Get a report of the expected and received values for each test stream.
+    pub fn report(&self) -> PuzzleReport {
+        let streams = self.tests.iter().map(|(id, node)| {
+            StreamReport {
+                name: self.names.get(id).cloned().unwrap_or_default(),
+                node: id,
+                state: node.state(),
+                details: node.report(),
+            }
+        }).collect();
+
+        let outcome = self.outcome();
+        let deadlock = if outcome == Some(Outcome::Deadlock) {
+            self.deadlock_report()
+        } else {
+            None
+        };
+
+        let livelock_period = if outcome == Some(Outcome::Livelock) {
+            self.livelock_period
+        } else {
+            None
+        };
+
+        PuzzleReport {
+            outcome: outcome,
+            cycles: self.cycles,
+            streams: streams,
+            deadlock: deadlock,
+            livelock_period: livelock_period,
+        }
+    }
+
+    /// Step the puzzle until it stops, and return its outcome.
+    pub fn run(&mut self) -> Outcome {
+        loop {
+            if let Some(outcome) = self.outcome() {
+                return outcome;
+            }
+
+            self.step();
+        }
+    }
+
+    /// Get the number of cycles that the puzzle may run for before it times out.
+    pub fn max_cycles(&self) -> Option<usize> {
+        self.max_cycles
+    }
+
+    /// Set the number of cycles that the puzzle may run for before it times out. If the limit is
+    /// `None`, then the puzzle never times out.
+    pub fn set_max_cycles(&mut self, max_cycles: Option<usize>) {
+        self.max_cycles = max_cycles;
+    }
+
+    /// Enable or disable livelock detection. When it is enabled, the state of the whole system is
+    /// recorded after every cycle and compared with a single saved state, and the puzzle stops
+    /// with `Outcome::Livelock` once a state is seen again. A repeat is found within a few periods
+    /// of the state first repeating. Detection is disabled by default, and has no effect if any
+    /// node in the puzzle can't describe its state.
+    pub fn set_livelock_detection(&mut self, enabled: bool) {
+        self.detector = if enabled { Some(CycleDetector::new()) } else { None };
+        self.livelock_period = None;
+    }
+
+    /// Start or stop profiling the nodes of the puzzle. See `Tis100::set_profiling`.
+    pub fn set_profiling(&mut self, enabled: bool) {
+        self.cpu.set_profiling(enabled);
+    }
+
+    /// Get the profile of the puzzle, if it is being profiled.
+    pub fn profile(&self) -> Option<Profile> {
+        self.cpu.profile()
+    }
+
+    /// Add a hook that is called after each phase of every cycle. See `Tis100::add_hook`.
+    pub fn add_hook(&mut self, hook: Rc<RefCell<Hook>>) {
+        self.cpu.add_hook(hook);
+    }
+
+    /// Add an observer that is told about the events of every cycle. See `Tis100::add_observer`.
+    pub fn add_observer(&mut self, observer: Rc<RefCell<Observer>>) {
+        self.cpu.add_observer(observer);
+    }
+
+    /// Get the CPU that executes the puzzle's programs. The test outputs are not part of it.
+    pub fn cpu(&self) -> &Tis100 {
+        &self.cpu
+    }
+
+    /// Start or stop collecting instruction coverage. See `Tis100::set_coverage`.
+    pub fn set_coverage(&mut self, enabled: bool) {
+        self.cpu.set_coverage(enabled);
+    }
+
+    /// Get the instruction coverage of the puzzle, if it is being collected.
+    pub fn coverage(&self) -> Option<Coverage> {
+        self.cpu.coverage()
+    }
+
+    /// Get the number of cycles after which the system repeats itself, if a repeated state has
+    /// been detected.
+    pub fn livelock_period(&self) -> Option<usize> {
+        self.livelock_period
+    }
+
     pub fn is_deadlocked(&self) -> bool {
         self.cpu.is_deadlocked()
     }
 
+    /// Get the registers of the node with the given ID, if it executes assembly.
+    pub fn registers(&self, node: usize) -> Option<Registers> {
+        self.cpu.registers(node)
+    }
+
+    /// Get the wait-for graph of the nodes, if the puzzle is deadlocked.
+    pub fn deadlock_report(&self) -> Option<DeadlockReport> {
+        self.cpu.deadlock_report()
+    }
+
+    /// Determine if the puzzle has halted because none of its nodes have anything left to execute.
+    pub fn is_halted(&self) -> bool {
+        self.cpu.is_halted()
+    }
+
     pub fn cycles(&self) -> usize {
         self.cycles
     }
 }
 
+/// Get the names of the streams that are checked by test nodes, by output.
+fn test_names(streams: &[Stream]) -> VecMap<String> {
+    let mut names = VecMap::new();
+
+    for stream in streams.iter() {
+        if stream.kind != spec::StreamKind::Input {
+            names.insert(stream.node, stream.name.clone());
+        }
+    }
+
+    names
+}
+
+/// A hook that is called after each phase of a `Tis100` cycle, with the state of the system at the
+/// end of that phase. Hooks can only inspect the system, so they can't change how it executes.
+///
+/// # Example
+///
+/// ```
+/// use std::cell::RefCell;
+/// use std::rc::Rc;
+/// use tis_100::machine::{Hook, Tis100};
+///
+/// struct Cycles(usize);
+///
+/// impl Hook for Cycles {
+///     fn commit(&mut self, _: &Tis100) {
+///         self.0 += 1;
+///     }
+/// }
+///
+/// let cycles = Rc::new(RefCell::new(Cycles(0)));
+/// let mut cpu = Tis100::new();
+/// cpu.add_hook(cycles.clone());
+///
+/// for _ in 0..3 {
+///     cpu.step();
+///     cpu.sync();
+///     cpu.commit();
+/// }
+///
+/// assert_eq!(cycles.borrow().0, 3);
+/// ```
+pub trait Hook {
+    /// Called after every node has stepped.
+    #[allow(unused)]
+    fn step(&mut self, cpu: &Tis100) {}
+
+    /// Called after every node has synchronized with the bus.
+    #[allow(unused)]
+    fn sync(&mut self, cpu: &Tis100) {}
+
+    /// Called after the writes of the cycle have been committed, at the end of the cycle.
+    #[allow(unused)]
+    fn commit(&mut self, cpu: &Tis100) {}
+}
+
+/// An observer of the events of a `Tis100` cycle. Unlike a `Hook`, which sees the whole system at
+/// the end of each phase, an observer is told about each thing that happened. Instructions are
+/// reported as they complete, and the other events are reported at the end of the cycle, before
+/// `cycle_end`.
+///
+/// Nothing is recorded for observers until the first one is added, so a system without observers
+/// runs as fast as before.
+///
+/// # Example
+///
+/// ```
+/// use std::cell::RefCell;
+/// use std::rc::Rc;
+/// use tis_100::save::parse_save;
+/// use tis_100::machine::{Observer, Sandbox};
+///
+/// struct Console(Vec<isize>);
+///
+/// impl Observer for Console {
+///     fn output(&mut self, output: usize, value: isize) {
+///         assert_eq!(output, 2);
+///         self.0.push(value);
+///     }
+/// }
+///
+/// let src = "@1\nMOV UP DOWN\n@5\nMOV UP DOWN\n@9\nMOV UP RIGHT\n@10\nMOV LEFT DOWN\n";
+/// let mut sandbox = Sandbox::from_save(&parse_save(src).unwrap());
+///
+/// let console = Rc::new(RefCell::new(Console(Vec::new())));
+/// sandbox.add_observer(console.clone());
+/// sandbox.write_console(42);
+///
+/// for _ in 0..5 {
+///     sandbox.step();
+/// }
+///
+/// assert_eq!(console.borrow().0, vec![42]);
+/// ```
+pub trait Observer {
+    /// Called when a node completes the instruction at `pc`. Jumps have completed, even if they
+    /// weren't taken, and writes complete once the value has been read.
+    #[allow(unused)]
+    fn instruction(&mut self, node: NodeId, pc: usize, instruction: Instruction) {}
+
+    /// Called for each value that was written to a port during the cycle.
+    #[allow(unused)]
+    fn write(&mut self, transfer: Transfer) {}
+
+    /// Called for each value that was read from a port during the cycle.
+    #[allow(unused)]
+    fn read(&mut self, transfer: Transfer) {}
+
+    /// Called when a node starts waiting to read or write a value.
+    #[allow(unused)]
+    fn blocked(&mut self, node: NodeId, action: IoAction) {}
+
+    /// Called when a node that was waiting to read or write a value stops waiting.
+    #[allow(unused)]
+    fn unblocked(&mut self, node: NodeId) {}
+
+    /// Called for each value that was written to an output during the cycle, after `write`.
+    #[allow(unused)]
+    fn output(&mut self, output: usize, value: isize) {}
+
+    /// Called at the end of every cycle, after all of the other events of the cycle. The first
+    /// cycle is 1.
+    #[allow(unused)]
+    fn cycle_end(&mut self, cycle: usize) {}
+}
+
 /// An empty TIS-100 CPU.
 pub struct Tis100 {
     nodes: VecMap<Box<Node>>,
     bus: IoBus,
     stalled: usize,
+    profiler: Option<Profiler>,
+    coverage: Option<Coverage>,
+    hooks: Vec<Rc<RefCell<Hook>>>,
+    observers: Vec<Rc<RefCell<Observer>>>,
+    completed: Vec<(NodeId, usize, isize)>,
+    blocked: VecMap<IoAction>,
+    cycles: usize,
 }
 
 impl Tis100 {
@@ -161,6 +728,13 @@ impl Tis100 {
             nodes: VecMap::new(),
             bus: IoBus::new(),
             stalled: 0,
+            profiler: None,
+            coverage: None,
+            hooks: Vec::new(),
+            observers: Vec::new(),
+            completed: Vec::new(),
+            blocked: VecMap::new(),
+            cycles: 0,
         };
         tis100.setup();
         tis100
@@ -219,41 +793,580 @@ impl Tis100 {
         self.bus.view(output + OUTPUT_0).read(UP)
     }
 
+    /// Determine if anything needs to know which instructions complete.
+    fn is_observed(&self) -> bool {
+        self.profiler.is_some() || self.coverage.is_some() || !self.observers.is_empty()
+    }
+
     /// Execute one instruction cycle on all nodes in the system.
     pub fn step(&mut self) {
+        let observed = self.is_observed();
+
         // Step each node
         for (id, node) in self.nodes.iter_mut() {
+            let before = if observed { node.registers() } else { None };
+
             let mut view = self.bus.view(id);
             node.step(&mut view);
+
+            if let (Some(before), Some(after)) = (before, node.registers()) {
+                if let Some(ref mut profiler) = self.profiler {
+                    profiler.record_mode(id, after.mode);
+                }
+
+                // A node that is still running after it steps has completed the instruction at its
+                // previous program counter.
+                if after.mode == Mode::Run {
+                    self.completed.push((id, before.pc, before.acc));
+                }
+            }
+        }
+
+        self.record_instructions();
+
+        for hook in self.hooks.iter() {
+            hook.borrow_mut().step(self);
         }
     }
 
     /// Synchronize reads and writes for each node.
     pub fn sync(&mut self) {
+        let observed = self.is_observed();
+
         // Synchronize writes and reads on each node
         for (id, node) in self.nodes.iter_mut() {
+            let before = if observed { node.registers() } else { None };
+
             let mut view = self.bus.view(id);
             node.sync(&mut view);
+
+            // A write completes once the value has been read, which unblocks the node.
+            if let (Some(before), Some(after)) = (before, node.registers()) {
+                if before.mode == Mode::Wrte && after.mode == Mode::Run {
+                    self.completed.push((id, before.pc, before.acc));
+                }
+            }
         }
 
-        // Check for deadlock
-        if self.nodes.iter().all(|(_, ref n)| n.is_stalled()) {
+        self.record_instructions();
+
+        if let Some(ref mut profiler) = self.profiler {
+            for transfer in self.bus.transfers() {
+                profiler.record_transfer(transfer.from, transfer.to);
+            }
+            profiler.record_cycle();
+        }
+
+        // Check for deadlock. Nodes that are waiting on IO are still making progress if values
+        // were passed between nodes during this cycle.
+        if !self.bus.has_traffic() && self.nodes.iter().all(|(_, ref n)| n.is_stalled()) {
             self.stalled += 1;
         } else {
             self.stalled = 0;
         }
 
+        for hook in self.hooks.iter() {
+            hook.borrow_mut().sync(self);
+        }
     }
 
     /// Commit all outstanding writes on the `IoBus`.
     pub fn commit(&mut self) {
+        if !self.observers.is_empty() {
+            self.notify_observers();
+        }
+
         // Commit writes so they are available on the next cycle.
         self.bus.commit();
+        self.cycles += 1;
+
+        for observer in self.observers.iter() {
+            observer.borrow_mut().cycle_end(self.cycles);
+        }
+
+        for hook in self.hooks.iter() {
+            hook.borrow_mut().commit(self);
+        }
+    }
+
+    /// Pass the instructions that completed during the last phase to the profiler, the coverage
+    /// and the observers.
+    fn record_instructions(&mut self) {
+        for (id, pc, acc) in self.completed.drain(..) {
+            let instruction = match self.nodes.get(id).and_then(|node| node.program()).and_then(|prog| prog.get(pc)) {
+                Some(&instruction) => instruction,
+                None => continue,
+            };
+
+            if let Some(ref mut profiler) = self.profiler {
+                profiler.record_instruction(id, pc);
+            }
+
+            if let Some(ref mut coverage) = self.coverage {
+                coverage.record_instruction(id, pc, instruction, acc);
+            }
+
+            for observer in self.observers.iter() {
+                observer.borrow_mut().instruction(id, pc, instruction);
+            }
+        }
+    }
+
+    /// Tell the observers about the values that were passed over the bus during the cycle, and
+    /// about the nodes that started or stopped waiting.
+    fn notify_observers(&mut self) {
+        for observer in self.observers.iter() {
+            let mut observer = observer.borrow_mut();
+
+            for &transfer in self.bus.written() {
+                observer.write(transfer);
+                if transfer.to >= OUTPUT_0 && transfer.to < OUTPUT_0 + NUM_OUTPUTS {
+                    observer.output(transfer.to - OUTPUT_0, transfer.value);
+                }
+            }
+
+            for &transfer in self.bus.transfers() {
+                observer.read(transfer);
+            }
+        }
+
+        for (id, node) in self.nodes.iter() {
+            let action = node.blocked_on().map(|(action, _)| action);
+            if action == self.blocked.get(id).cloned() {
+                continue;
+            }
+
+            for observer in self.observers.iter() {
+                match action {
+                    Some(action) => observer.borrow_mut().blocked(id, action),
+                    None => observer.borrow_mut().unblocked(id),
+                }
+            }
+
+            match action {
+                Some(action) => self.blocked.insert(id, action),
+                None => self.blocked.remove(id),
+            };
+        }
+    }
+
+    /// Add a hook that is called after each phase of every cycle. The caller can keep a clone of
+    /// the hook to read its results.
+    pub fn add_hook(&mut self, hook: Rc<RefCell<Hook>>) {
+        self.hooks.push(hook);
+    }
+
+    /// Add an observer that is told about the events of every cycle. The caller can keep a clone
+    /// of the observer to read its results.
+    pub fn add_observer(&mut self, observer: Rc<RefCell<Observer>>) {
+        self.observers.push(observer);
+        self.bus.set_logging(true);
+    }
+
+    /// Get the bus that connects the nodes.
+    pub fn bus(&self) -> &IoBus {
+        &self.bus
+    }
+
+    /// Determine if a node has been added with the given ID.
+    pub fn has_node(&self, node: usize) -> bool {
+        self.nodes.contains_key(node)
+    }
+
+    /// Get the program of the node with the given ID, if it executes assembly.
+    pub fn program(&self, node: usize) -> Option<&Program> {
+        self.nodes.get(node).and_then(|n| n.program())
     }
 
     /// Determine if the system is deadlocked. The system is considered deadlocked if all
-    /// execution nodes are reading or writing for more than 1 cycle.
+    /// execution nodes are reading or writing, and no values have been passed between nodes, for
+    /// more than 1 cycle.
     pub fn is_deadlocked(&self) -> bool {
         self.stalled > 1
     }
+
+    /// Get the registers of the node with the given ID, if it executes assembly.
+    pub fn registers(&self, node: usize) -> Option<Registers> {
+        self.nodes.get(node).and_then(|n| n.registers())
+    }
+
+    /// Start or stop profiling the system. Starting the profiler discards any previous profile.
+    pub fn set_profiling(&mut self, enabled: bool) {
+        self.profiler = if enabled { Some(Profiler::new()) } else { None };
+        self.bus.set_logging(enabled || !self.observers.is_empty());
+    }
+
+    /// Get the profile of every cycle since profiling started, if the system is being profiled.
+    pub fn profile(&self) -> Option<Profile> {
+        self.profiler.as_ref().map(|profiler| profiler.profile())
+    }
+
+    /// Start or stop collecting instruction coverage for the programs of the nodes that have been
+    /// added. Starting discards any previous coverage.
+    pub fn set_coverage(&mut self, enabled: bool) {
+        self.coverage = if enabled {
+            let programs = self.nodes.iter()
+                .filter_map(|(id, node)| node.program().map(|program| (id, program)))
+                .collect();
+            Some(Coverage::new(&programs))
+        } else {
+            None
+        };
+    }
+
+    /// Get the coverage of every cycle since collection started, if coverage is being collected.
+    pub fn coverage(&self) -> Option<Coverage> {
+        self.coverage.clone()
+    }
+
+    /// Get the wait-for graph of the nodes, if the system is deadlocked. A node that reads from
+    /// `ANY` is only reported as waiting on the ports that are connected to another node.
+    pub fn deadlock_report(&self) -> Option<DeadlockReport> {
+        if !self.is_deadlocked() {
+            return None;
+        }
+
+        let mut waits = Vec::new();
+        for (id, node) in self.nodes.iter() {
+            if let Some((action, ports)) = node.blocked_on() {
+                let any = ports.len() > 1;
+
+                for port in ports {
+                    let peer = match action {
+                        IoAction::Read => self.bus.input_peer(id, port),
+                        IoAction::Write => self.bus.output_peer(id, port),
+                    };
+
+                    let peer_state = match peer.and_then(|peer| self.nodes.get(peer)) {
+                        Some(peer) => PeerState::of(&**peer),
+                        None => PeerState::Disconnected,
+                    };
+
+                    if any && peer.is_none() {
+                        continue;
+                    }
+
+                    waits.push(Wait {
+                        node: id,
+                        action: action,
+                        port: port,
+                        peer: peer,
+                        peer_state: peer_state,
+                        cyclic: false,
+                    });
+                }
+            }
+        }
+
+        Some(DeadlockReport::new(waits))
+    }
+
+    /// Feed the state of every node and the bus into the hasher. Returns `false` if any node can't
+    /// describe its state.
+    pub fn hash_state(&self, mut state: &mut Hasher) -> bool {
+        for (id, node) in self.nodes.iter() {
+            id.hash(&mut state);
+            if !node.hash_state(state) {
+                return false;
+            }
+        }
+
+        self.bus.hash_state(state);
+        true
+    }
+
+    /// Determine if the system has halted. The system is considered halted if it is deadlocked,
+    /// but none of the nodes are waiting to read or write a value.
+    pub fn is_halted(&self) -> bool {
+        self.is_deadlocked() && self.nodes.iter().all(|(_, ref n)| !n.is_waiting())
+    }
+}
+
+#[test]
+fn test_puzzle_from_builder() {
+    use save::parse_save;
+    use spec::{SpecBuilder, Tile};
+
+    // Double each value, and route it around a damaged tile to an output in another column.
+    let save = parse_save("@2\nMOV UP ACC\nADD ACC\nMOV ACC RIGHT\n@3\nMOV LEFT DOWN\n@7\nMOV UP DOWN\n@11\nMOV UP DOWN\n").unwrap();
+    let spec = SpecBuilder::new()
+        .name("DOUBLER")
+        .description("> DOUBLE THE VALUES FROM IN.X")
+        .tile(6, Tile::Damaged)
+        .input("IN.X", 2, vec![1, -2, 3])
+        .output("OUT.Y", 3, vec![2, -4, 6])
+        .build(Save::new()).ok().unwrap();
+
+    let mut puzzle = Puzzle::new(&spec, &save, 0).ok().unwrap();
+    while puzzle.state() == Testing {
+        puzzle.step();
+    }
+
+    assert_eq!(puzzle.state(), Passed);
+}
+
+#[test]
+fn test_puzzle_outcome() {
+    use fixtures::pipeline;
+    use save::parse_save;
+
+    // Values moving through a pipeline of stalled nodes are not a deadlock.
+    let (spec, save) = pipeline();
+    let mut puzzle = Puzzle::new(&spec, &save, 0).ok().unwrap();
+    assert_eq!(puzzle.run(), Outcome::Passed);
+
+    // Writing the wrong value fails as soon as it is received.
+    let save = parse_save("@1\nMOV UP DOWN\n@5\nMOV UP DOWN\n@9\nMOV 7 DOWN\n").unwrap();
+    let mut puzzle = Puzzle::new(&spec, &save, 0).ok().unwrap();
+    assert_eq!(puzzle.run(), Outcome::Failed);
+    assert!(puzzle.cycles() < 10);
+
+    // Nothing reaches the output, and the program never stalls.
+    let save = parse_save("@0\nADD 1\n").unwrap();
+    let mut puzzle = Puzzle::new(&spec, &save, 0).ok().unwrap();
+    puzzle.set_max_cycles(Some(50));
+    assert_eq!(puzzle.run(), Outcome::Timeout);
+    assert_eq!(puzzle.cycles(), 50);
+
+    // A node waits forever for a value that is never written.
+    let save = parse_save("@1\nMOV UP ACC\nMOV RIGHT ACC\n").unwrap();
+    let mut puzzle = Puzzle::new(&spec, &save, 0).ok().unwrap();
+    assert_eq!(puzzle.run(), Outcome::Deadlock);
+
+    let mut puzzle = Puzzle::new(&spec, &Save::new(), 0).ok().unwrap();
+    assert_eq!(puzzle.run(), Outcome::Halt);
+}
+
+#[test]
+fn test_puzzle_livelock() {
+    use fixtures::pipeline;
+    use save::parse_save;
+
+    let (spec, _) = pipeline();
+
+    // The node keeps running, but it is back where it started every 2 cycles.
+    let save = parse_save("@0\nADD 1\nSUB 1\n").unwrap();
+    let mut puzzle = Puzzle::new(&spec, &save, 0).ok().unwrap();
+    puzzle.set_max_cycles(Some(50));
+    puzzle.set_livelock_detection(true);
+    assert_eq!(puzzle.run(), Outcome::Livelock);
+    assert_eq!(puzzle.livelock_period(), Some(2));
+    assert!(puzzle.cycles() < 10);
+    assert_eq!(puzzle.report().livelock_period, Some(2));
+
+    // Without detection, the same program times out.
+    let mut puzzle = Puzzle::new(&spec, &save, 0).ok().unwrap();
+    puzzle.set_max_cycles(Some(50));
+    assert_eq!(puzzle.run(), Outcome::Timeout);
+
+    // A program whose state never repeats isn't a livelock.
+    let save = parse_save("@0\nADD 1\n").unwrap();
+    let mut puzzle = Puzzle::new(&spec, &save, 0).ok().unwrap();
+    puzzle.set_max_cycles(Some(50));
+    puzzle.set_livelock_detection(true);
+    assert_eq!(puzzle.run(), Outcome::Timeout);
+
+    // Deadlocked systems also repeat their state, but are still reported as deadlocks.
+    let save = parse_save("@1\nMOV UP ACC\nMOV RIGHT ACC\n").unwrap();
+    let mut puzzle = Puzzle::new(&spec, &save, 0).ok().unwrap();
+    puzzle.set_livelock_detection(true);
+    assert_eq!(puzzle.run(), Outcome::Deadlock);
+}
+
+#[test]
+fn test_cycle_detector() {
+    // The states 0, 1, 2, 3, 4 lead into a loop of 5, 6, 7.
+    let state = |cycle: usize| if cycle < 5 { cycle } else { 5 + (cycle - 5) % 3 };
+    let mut detector = CycleDetector::new();
+    let mut period = None;
+    let mut cycle = 0;
+
+    while period.is_none() && cycle < 100 {
+        let mut snapshot = Snapshot::default();
+        state(cycle).hash(&mut snapshot);
+        period = detector.check(snapshot, cycle);
+        cycle += 1;
+    }
+
+    assert_eq!(period, Some(3));
+    assert!(cycle < 20);
+
+    // States that never repeat are never matched, however many of them there are.
+    let mut detector = CycleDetector::new();
+    for cycle in 0..10000 {
+        let mut snapshot = Snapshot::default();
+        cycle.hash(&mut snapshot);
+        assert_eq!(detector.check(snapshot, cycle), None);
+    }
+}
+
+#[test]
+fn test_puzzle_profile() {
+    use fixtures::pipeline;
+    let (spec, save) = pipeline();
+    let mut puzzle = Puzzle::new(&spec, &save, 0).ok().unwrap();
+    assert_eq!(puzzle.profile(), None);
+
+    puzzle.set_profiling(true);
+    assert_eq!(puzzle.run(), Outcome::Passed);
+
+    let profile = puzzle.profile().unwrap();
+    assert_eq!(profile.cycles, puzzle.cycles());
+    assert_eq!(profile.nodes.len(), NUM_NODES);
+
+    for node in profile.nodes.iter() {
+        assert_eq!(node.run + node.read + node.write + node.idle, profile.cycles);
+    }
+
+    assert_eq!(profile.nodes[0].idle, profile.cycles);
+    assert_eq!(profile.nodes[5].instructions, vec![3]);
+    assert!(profile.nodes[5].read > 0 && profile.nodes[5].write > 0);
+
+    let edges = profile.edges.iter().map(|edge| (edge.from, edge.to, edge.values)).collect::<Vec<_>>();
+    assert_eq!(edges, vec![(1, 5, 3), (5, 9, 3), (9, OUTPUT_1, 3), (INPUT_1, 1, 3)]);
+}
+
+#[test]
+fn test_puzzle_report() {
+    use save::parse_save;
+    use spec::SpecBuilder;
+
+    let spec = SpecBuilder::new()
+        .input("IN.A", 1, vec![1, 2])
+        .output("OUT.A", 2, vec![1, 2])
+        .image("IMAGE", 0, vec![0; 30 * 18])
+        .build(Save::new()).ok().unwrap();
+
+    let save = parse_save("@8\nMOV 0 DOWN\nMOV 0 DOWN\nMOV 3 DOWN\n@10\nMOV 2 DOWN\n").unwrap();
+    let mut puzzle = Puzzle::new(&spec, &save, 0).ok().unwrap();
+    for _ in 0..10 {
+        puzzle.step();
+    }
+
+    let report = puzzle.report();
+    assert_eq!(report.outcome, Some(Outcome::Failed));
+    assert_eq!(report.streams.len(), 2);
+    assert_eq!(report.streams[0].name, "IMAGE");
+    assert_eq!(report.streams[0].details, Some(TestReport::Image {
+        width: 30,
+        height: 18,
+        mismatches: vec![(0, 0)],
+    }));
+    assert_eq!(report.streams[1].name, "OUT.A");
+    assert_eq!(report.streams[1].state, Failed);
+    match report.streams[1].details {
+        Some(TestReport::Output { ref expected, ref received, mismatch }) => {
+            assert_eq!(*expected, vec![1, 2]);
+            assert_eq!(received[0], 2);
+            assert_eq!(mismatch, Some(0));
+        },
+        _ => panic!("expected an output report"),
+    }
+
+    let text = report.to_string();
+    assert!(text.starts_with("FAILED AFTER"));
+    assert!(text.contains("1 OF 540 PIXELS DO NOT MATCH"));
+    assert!(text.contains("<- FIRST MISMATCH"));
+}
+
+#[test]
+fn test_deadlock_report() {
+    use core::Port::*;
+    use save::parse_save;
+    use spec::{SpecBuilder, Tile};
+
+    let spec = SpecBuilder::new()
+        .tile(2, Tile::Damaged)
+        .input("IN.A", 1, vec![1, 2, 3])
+        .output("OUT.A", 1, vec![1, 2, 3])
+        .build(Save::new()).ok().unwrap();
+
+    // 4 and 5 both wait to read from each other, and 1 waits to write to the damaged node.
+    let save = parse_save("@1\nMOV 1 RIGHT\n@4\nMOV RIGHT ACC\n@5\nMOV LEFT ACC\n").unwrap();
+    let mut puzzle = Puzzle::new(&spec, &save, 0).ok().unwrap();
+    assert_eq!(puzzle.run(), Outcome::Deadlock);
+
+    let report = puzzle.report().deadlock.unwrap();
+    assert_eq!(report.cycles, vec![vec![4, 5]]);
+
+    let wait = report.waits.iter().find(|wait| wait.node == 1).unwrap();
+    assert_eq!(wait.action, IoAction::Write);
+    assert_eq!(wait.port, RIGHT);
+    assert_eq!(wait.peer, Some(2));
+    assert_eq!(wait.peer_state, PeerState::Damaged);
+    assert!(!wait.cyclic);
+
+    let wait = report.waits.iter().find(|wait| wait.node == 4).unwrap();
+    assert_eq!(wait.peer, Some(5));
+    assert!(wait.cyclic);
+
+    // The input is waiting for node 1 to read.
+    let wait = report.waits.iter().find(|wait| wait.node == INPUT_1).unwrap();
+    assert_eq!(wait.peer_state, PeerState::Blocked);
+}
+
+#[test]
+fn test_puzzle_observer() {
+    use fixtures::pipeline;
+    use core::Port::DOWN;
+
+    #[derive(Default)]
+    struct Events {
+        instructions: Vec<(NodeId, usize)>,
+        writes: Vec<Transfer>,
+        reads: Vec<Transfer>,
+        blocked: Vec<(NodeId, Option<IoAction>)>,
+        outputs: Vec<(usize, isize)>,
+        cycles: usize,
+    }
+
+    impl Observer for Events {
+        fn instruction(&mut self, node: NodeId, pc: usize, _: Instruction) {
+            self.instructions.push((node, pc));
+        }
+
+        fn write(&mut self, transfer: Transfer) {
+            self.writes.push(transfer);
+        }
+
+        fn read(&mut self, transfer: Transfer) {
+            self.reads.push(transfer);
+        }
+
+        fn blocked(&mut self, node: NodeId, action: IoAction) {
+            self.blocked.push((node, Some(action)));
+        }
+
+        fn unblocked(&mut self, node: NodeId) {
+            self.blocked.push((node, None));
+        }
+
+        fn output(&mut self, output: usize, value: isize) {
+            self.outputs.push((output, value));
+        }
+
+        fn cycle_end(&mut self, cycle: usize) {
+            assert_eq!(cycle, self.cycles + 1);
+            self.cycles = cycle;
+        }
+    }
+
+    let (spec, save) = pipeline();
+    let mut puzzle = Puzzle::new(&spec, &save, 0).ok().unwrap();
+
+    let events = Rc::new(RefCell::new(Events::default()));
+    puzzle.add_observer(events.clone());
+    assert_eq!(puzzle.run(), Outcome::Passed);
+
+    let events = events.borrow();
+    assert_eq!(events.cycles, puzzle.cycles());
+    assert_eq!(events.instructions.iter().filter(|&&(node, _)| node == 5).count(), 3);
+    assert_eq!(events.outputs, vec![(1, 1), (1, 2), (1, 3)]);
+    assert!(events.writes.contains(&Transfer { from: 1, port: DOWN, to: 5, value: 2 }));
+    assert!(events.reads.contains(&Transfer { from: 1, port: DOWN, to: 5, value: 2 }));
+    assert_eq!(events.reads.iter().filter(|t| t.to == OUTPUT_1).count(), 3);
+
+    // Node 5 waits to read the first value, then waits for node 9 to read it.
+    let node_5 = events.blocked.iter().filter(|&&(node, _)| node == 5).map(|&(_, action)| action);
+    assert_eq!(node_5.take(3).collect::<Vec<_>>(), vec![Some(IoAction::Read), Some(IoAction::Write), None]);
 }
@@ -1,5 +1,6 @@
-use super::Node;
-use core::{Program, Port, Instruction, Source, Register};
+use std::hash::{Hash, Hasher};
+use super::{Node, Registers, IoAction};
+use core::{Program, Port, Instruction, Source, Register, IoRegister};
 use core::Port::*;
 use core::Instruction::*;
 use core::Source::*;
@@ -11,10 +12,19 @@ use io::IoBusView;
 #[derive(Debug)]
 pub struct DamagedExecutionNode;
 
-impl Node for DamagedExecutionNode {}
+impl Node for DamagedExecutionNode {
+    fn is_damaged(&self) -> bool {
+        true
+    }
+
+    fn hash_state(&self, _: &mut Hasher) -> bool {
+        true
+    }
+}
 
 /// An execution mode of a `BasicExecutionNode`.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
 pub enum Mode {
     Idle,
     Run,
@@ -96,6 +106,18 @@ impl BasicExecutionNode {
         &self.mode
     }
 
+    /// Get the ports that an IO register refers to, in the order that they are used.
+    fn io_ports(&self, reg: IoRegister, action: IoAction) -> Vec<Port> {
+        match reg {
+            DIR(port) => vec![port],
+            ANY => match action {
+                IoAction::Read => vec![LEFT, RIGHT, UP, DOWN],
+                IoAction::Write => vec![UP, DOWN, LEFT, RIGHT],
+            },
+            LAST => self.last.iter().cloned().collect(),
+        }
+    }
+
     /// Increment the program counter.
     fn inc_pc(&mut self) {
         self.pc += 1;
@@ -108,8 +130,9 @@ impl BasicExecutionNode {
     fn set_pc(&mut self, pc: isize) {
         if pc < 0 {
             self.pc = 0;
-        } else if pc as usize > self.program.len() {
-            self.pc = self.program.len() as isize - 1;
+        } else if pc as usize >= self.program.len() {
+            // A label after the last instruction wraps around to the first one.
+            self.pc = 0;
         } else {
             self.pc = pc;
         }
@@ -120,8 +143,9 @@ impl BasicExecutionNode {
         self.program.get(self.pc as usize).map(|&i| i)
     }
 
-    /// Evaluate the given instruction.
-    fn eval(&mut self, instruction: Instruction, io: &mut IoBusView) {
+    /// Evaluate the given instruction. Returns `true` if the instruction jumped, in which case the
+    /// program counter is already pointing at the next instruction to execute.
+    fn eval(&mut self, instruction: Instruction, io: &mut IoBusView) -> bool {
         match instruction {
             Nop => (),
             Mov(src, dst) => if let Some(val) = self.read(io, src) {
@@ -141,24 +165,35 @@ impl BasicExecutionNode {
                 self.acc -= val;
             },
             Neg => self.acc = -self.acc,
-            Jmp(pc) => self.set_pc(pc),
+            Jmp(pc) => {
+                self.set_pc(pc);
+                return true;
+            },
             Jez(pc) => if self.acc == 0 {
                 self.set_pc(pc);
+                return true;
             },
             Jnz(pc) => if self.acc != 0 {
                 self.set_pc(pc);
+                return true;
             },
             Jgz(pc) => if self.acc > 0 {
                 self.set_pc(pc);
+                return true;
             },
             Jlz(pc) => if self.acc < 0 {
                 self.set_pc(pc);
+                return true;
             },
             Jro(src) => if let Some(off) = self.read(io, src) {
-                let pc = self.pc + off;
+                // Unlike a jump to a label, JRO stops at the last instruction.
+                let pc = (self.pc + off).min(self.program.len() as isize - 1);
                 self.set_pc(pc);
+                return true;
             },
         }
+
+        false
     }
 
     /// Read a value from the given register.
@@ -214,8 +249,8 @@ impl Node for BasicExecutionNode {
         if self.mode != Wrte {
             if let Some(instruction) = self.fetch() {
                 self.mode = Run;
-                self.eval(instruction, io);
-                if self.mode == Run {
+                let jumped = self.eval(instruction, io);
+                if self.mode == Run && !jumped {
                     self.inc_pc();
                 }
             }
@@ -238,6 +273,53 @@ impl Node for BasicExecutionNode {
     fn is_stalled(&self) -> bool {
         self.mode != Run
     }
+
+    /// An execution node is waiting if it is blocked on a read or a write.
+    fn is_waiting(&self) -> bool {
+        self.mode == Read || self.mode == Wrte
+    }
+
+    /// A node that is reading is blocked on the source of its current instruction, and a node that
+    /// is writing is blocked on the destination.
+    fn blocked_on(&self) -> Option<(IoAction, Vec<Port>)> {
+        let instruction = match self.program.get(self.pc as usize) {
+            Some(&instruction) => instruction,
+            None => return None,
+        };
+
+        let (action, reg) = match (&self.mode, instruction) {
+            (&Read, Mov(REG(IO(reg)), _)) |
+            (&Read, Add(REG(IO(reg)))) |
+            (&Read, Sub(REG(IO(reg)))) |
+            (&Read, Jro(REG(IO(reg)))) => (IoAction::Read, reg),
+            (&Wrte, Mov(_, IO(reg))) => (IoAction::Write, reg),
+            _ => return None,
+        };
+
+        Some((action, self.io_ports(reg, action)))
+    }
+
+    fn program(&self) -> Option<&Program> {
+        Some(&self.program)
+    }
+
+    fn registers(&self) -> Option<Registers> {
+        Some(Registers {
+            acc: self.acc,
+            bak: self.bak,
+            pc: self.pc as usize,
+            mode: self.mode,
+        })
+    }
+
+    fn hash_state(&self, mut state: &mut Hasher) -> bool {
+        self.pc.hash(&mut state);
+        self.mode.hash(&mut state);
+        self.acc.hash(&mut state);
+        self.bak.hash(&mut state);
+        self.last.hash(&mut state);
+        true
+    }
 }
 
 
@@ -262,3 +344,57 @@ fn test_clamp_value() {
     assert_eq!(clamp_value(-999), -999);
     assert_eq!(clamp_value(-998), -998);
 }
+
+#[test]
+fn test_jump() {
+    use io::IoBus;
+    use parse::parse_program;
+
+    let mut bus = IoBus::new();
+    bus.connect_full(0, 1, DOWN);
+
+    let prog = parse_program("JMP L\nMOV 1 ACC\nL: ADD 5\nJRO -2\n").unwrap();
+    let mut node = BasicExecutionNode::with_program(prog);
+
+    for _ in 0..5 {
+        node.step(&mut bus.view(0));
+        node.sync(&mut bus.view(0));
+    }
+
+    // JMP skips MOV 1 ACC, and JRO lands on it.
+    assert_eq!(node.acc, 6);
+    assert_eq!(node.pc, 3);
+}
+
+#[test]
+fn test_jump_past_end() {
+    use io::IoBus;
+    use parse::parse_program;
+
+    let mut bus = IoBus::new();
+    bus.connect_full(0, 1, DOWN);
+
+    // A jump to a label on a trailing line wraps around to the first instruction.
+    let prog = parse_program("ADD 1\nJEZ END\nJMP END\nADD 10\nEND:\n").unwrap();
+    let mut node = BasicExecutionNode::with_program(prog);
+
+    for _ in 0..4 {
+        node.step(&mut bus.view(0));
+        node.sync(&mut bus.view(0));
+    }
+
+    assert_eq!(node.acc, 2);
+    assert_eq!(node.pc, 1);
+
+    // JRO stops at the last instruction, even when it lands just past it.
+    let prog = parse_program("JRO 2\nADD 1\n").unwrap();
+    let mut node = BasicExecutionNode::with_program(prog);
+
+    for _ in 0..2 {
+        node.step(&mut bus.view(0));
+        node.sync(&mut bus.view(0));
+    }
+
+    assert_eq!(node.acc, 1);
+    assert_eq!(node.pc, 0);
+}
@@ -1,14 +1,24 @@
 //! Types of nodes used in the TIS-100.
 
-pub use self::exec::{BasicExecutionNode, DamagedExecutionNode};
-pub use self::stack::StackMemoryNode;
+pub use self::exec::{BasicExecutionNode, DamagedExecutionNode, Mode};
+pub use self::stack::{StackMemoryNode, STACK_CAPACITY};
+pub use self::queue::{QueueMemoryNode, QUEUE_CAPACITY};
+pub use self::ram::{RandomAccessMemoryNode, RAM_SIZE};
 pub use self::test::{TestInputNode, TestOutputNode, TestImageNode};
 
 mod exec;
 mod stack;
+mod queue;
+mod ram;
 mod test;
 
+use std::hash::Hasher;
 use io::IoBusView;
+use core::{Port, Program};
+use core::Port::*;
+
+/// The order in which memory nodes accept values that are written to them in the same cycle.
+const WRITE_ORDER: [Port; 4] = [LEFT, RIGHT, UP, DOWN];
 
 /// Interface for nodes in a TIS-100 system.
 pub trait Node {
@@ -29,15 +39,92 @@ pub trait Node {
     fn is_stalled(&self) -> bool {
         true
     }
+
+    /// Determine if a node is stalled because it is waiting to read or write a value, rather than
+    /// because it has nothing to execute. Nodes which can't execute assembly are never waiting.
+    fn is_waiting(&self) -> bool {
+        false
+    }
+
+    /// Get the registers of a node that executes assembly. Other nodes have no registers.
+    fn registers(&self) -> Option<Registers> {
+        None
+    }
+
+    /// Get the ports that a node is blocked on, if it is blocked on a read or a write. A read from
+    /// `ANY` is blocked on every port.
+    fn blocked_on(&self) -> Option<(IoAction, Vec<Port>)> {
+        None
+    }
+
+    /// Determine if a node is damaged. Damaged nodes never read or write.
+    fn is_damaged(&self) -> bool {
+        false
+    }
+
+    /// Get the program of a node that executes assembly.
+    fn program(&self) -> Option<&Program> {
+        None
+    }
+
+    /// Feed everything that affects the node's future behavior into the hasher, so that repeated
+    /// states of a system can be detected. Returns `false` if the node can't describe its state,
+    /// in which case repeated states can't be detected for the system.
+    #[allow(unused)]
+    fn hash_state(&self, state: &mut Hasher) -> bool {
+        false
+    }
 }
 
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+/// The kind of IO operation that a node can be blocked on.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IoAction {
+    Read,
+    Write,
+}
+
+/// A snapshot of the registers of a node that executes assembly.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize)]
+pub struct Registers {
+    pub acc: isize,
+    pub bak: isize,
+    pub pc: usize,
+    pub mode: Mode,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum TestState {
     Testing,
     Passed,
     Failed,
 }
 
+/// Details of what a test node has received, used to explain why a test failed.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum TestReport {
+    /// An output stream. `mismatch` is the index of the first received value that doesn't match
+    /// the expected value, or the index of the first extra value if too many were received.
+    Output {
+        expected: Vec<isize>,
+        received: Vec<isize>,
+        mismatch: Option<usize>,
+    },
+    /// An image stream, with the `(row, column)` coordinates of the pixels that don't match.
+    Image {
+        width: usize,
+        height: usize,
+        mismatches: Vec<(usize, usize)>,
+    },
+}
+
 pub trait TestNode: Node {
     fn state(&self) -> TestState;
+
+    /// Get the details of the test. Test nodes that can't describe their results return `None`.
+    fn report(&self) -> Option<TestReport> {
+        None
+    }
 }
@@ -0,0 +1,117 @@
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+use super::{Node, WRITE_ORDER};
+use io::IoBusView;
+
+/// The number of values that a `QueueMemoryNode` can hold.
+pub const QUEUE_CAPACITY: usize = 15;
+
+/// A node which stores values written to it in a queue. When the node is read from it will remove
+/// the oldest value from the queue and return it.
+///
+/// The queue follows the same rules as `StackMemoryNode`: it holds at most `QUEUE_CAPACITY`
+/// values, writers stall while it is full, values written in the same cycle are added in the order
+/// left, right, up, down, and reads are served before writes.
+#[derive(Debug)]
+pub struct QueueMemoryNode {
+    queue: VecDeque<isize>,
+    offer: Option<isize>,
+}
+
+impl QueueMemoryNode {
+    /// Construct a new, empty `QueueMemoryNode`.
+    pub fn new() -> QueueMemoryNode {
+        QueueMemoryNode {
+            queue: VecDeque::new(),
+            offer: None,
+        }
+    }
+
+    /// Get the values in the queue, in the order that they will be read.
+    pub fn values(&self) -> Vec<isize> {
+        self.offer.iter().chain(self.queue.iter()).cloned().collect()
+    }
+
+    /// Get the number of values in the queue.
+    pub fn len(&self) -> usize {
+        self.queue.len() + self.offer.iter().count()
+    }
+
+    /// Check if the queue is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Check if the queue is full. Writes to a full queue stall until a value is read.
+    pub fn is_full(&self) -> bool {
+        self.len() >= QUEUE_CAPACITY
+    }
+}
+
+impl Node for QueueMemoryNode {
+    /// Any values that have been written to this node are added to the back of the queue, as long
+    /// as there is room for them.
+    fn step(&mut self, io: &mut IoBusView) {
+        for &dir in WRITE_ORDER.iter() {
+            if self.is_full() {
+                break;
+            }
+
+            if let Some(val) = io.read(dir) {
+                self.queue.push_back(val);
+            }
+        }
+    }
+
+    /// At the end of each cycle, check if the offered value was read and make the front of the
+    /// queue available on all ports.
+    fn sync(&mut self, io: &mut IoBusView) {
+        if self.offer.is_some() && !io.is_blocked() {
+            self.offer = None;
+        }
+
+        if self.offer.is_none() {
+            if let Some(val) = self.queue.pop_front() {
+                for &dir in WRITE_ORDER.iter() {
+                    io.write(dir, val);
+                }
+                self.offer = Some(val);
+            }
+        }
+    }
+
+    fn hash_state(&self, mut state: &mut Hasher) -> bool {
+        self.queue.hash(&mut state);
+        self.offer.hash(&mut state);
+        true
+    }
+}
+
+#[test]
+fn test_queue_order() {
+    use io::IoBus;
+    use core::Port::*;
+
+    let mut bus = IoBus::new();
+    bus.connect_full(0, 1, RIGHT);
+    let mut queue = QueueMemoryNode::new();
+
+    for &val in [1, 2, 3].iter() {
+        bus.view(1).write(LEFT, val);
+        bus.commit();
+        queue.step(&mut bus.view(0));
+        queue.sync(&mut bus.view(0));
+        bus.commit();
+    }
+
+    assert_eq!(queue.values(), vec![1, 2, 3]);
+
+    for &val in [1, 2, 3].iter() {
+        assert_eq!(bus.view(1).read(LEFT), Some(val));
+        queue.step(&mut bus.view(0));
+        queue.sync(&mut bus.view(0));
+        bus.commit();
+    }
+
+    assert!(queue.is_empty());
+}
@@ -0,0 +1,132 @@
+use std::hash::{Hash, Hasher};
+use super::{Node, WRITE_ORDER};
+use io::IoBusView;
+
+/// The number of values that a `RandomAccessMemoryNode` can hold.
+pub const RAM_SIZE: usize = 16;
+
+/// A node which stores values at addresses. Writing a value to the node first sets the address,
+/// which is wrapped to the size of the memory. Once an address is set, the value at that address
+/// is available on all ports. Reading it, or writing a new value to store at the address, clears
+/// the address again.
+///
+/// Values written in the same cycle are handled in the order left, right, up, down, and reads are
+/// served before writes.
+#[derive(Debug)]
+pub struct RandomAccessMemoryNode {
+    memory: [isize; RAM_SIZE],
+    address: Option<usize>,
+    offer: Option<isize>,
+    writes: Vec<isize>,
+}
+
+impl RandomAccessMemoryNode {
+    /// Construct a new `RandomAccessMemoryNode` with every value set to zero.
+    pub fn new() -> RandomAccessMemoryNode {
+        RandomAccessMemoryNode {
+            memory: [0; RAM_SIZE],
+            address: None,
+            offer: None,
+            writes: Vec::new(),
+        }
+    }
+
+    /// Get the values in memory, in the order of their addresses.
+    pub fn values(&self) -> Vec<isize> {
+        self.memory.to_vec()
+    }
+
+    /// Get the address that was most recently set, if it hasn't been used yet.
+    pub fn address(&self) -> Option<usize> {
+        self.address
+    }
+}
+
+impl Node for RandomAccessMemoryNode {
+    /// Accept the values that have been written to this node. They are handled during `sync`, once
+    /// it is known whether the value at the current address has been read.
+    fn step(&mut self, io: &mut IoBusView) {
+        for &dir in WRITE_ORDER.iter() {
+            if let Some(val) = io.read(dir) {
+                self.writes.push(val);
+            }
+        }
+    }
+
+    /// At the end of each cycle, check if the offered value was read, then handle the writes from
+    /// this cycle and make the value at the current address available on all ports.
+    fn sync(&mut self, io: &mut IoBusView) {
+        if self.offer.is_some() && !io.is_blocked() {
+            self.offer = None;
+            self.address = None;
+        }
+
+        for val in self.writes.drain(..) {
+            match self.address {
+                Some(address) => {
+                    self.memory[address] = val;
+                    self.address = None;
+                },
+                None => {
+                    let size = RAM_SIZE as isize;
+                    self.address = Some((((val % size) + size) % size) as usize);
+                },
+            }
+        }
+
+        let value = self.address.map(|address| self.memory[address]);
+        if value != self.offer {
+            io.retract();
+            if let Some(val) = value {
+                for &dir in WRITE_ORDER.iter() {
+                    io.write(dir, val);
+                }
+            }
+            self.offer = value;
+        }
+    }
+
+    fn hash_state(&self, mut state: &mut Hasher) -> bool {
+        self.memory.hash(&mut state);
+        self.address.hash(&mut state);
+        self.offer.hash(&mut state);
+        self.writes.hash(&mut state);
+        true
+    }
+}
+
+#[test]
+fn test_ram() {
+    use io::IoBus;
+    use core::Port::*;
+
+    let mut bus = IoBus::new();
+    bus.connect_full(0, 1, RIGHT);
+    let mut ram = RandomAccessMemoryNode::new();
+
+    // Store 42 at address 3, and then load it back.
+    for &val in [3, 42, 19].iter() {
+        bus.view(1).write(LEFT, val);
+        bus.commit();
+        ram.step(&mut bus.view(0));
+        ram.sync(&mut bus.view(0));
+        bus.commit();
+    }
+
+    assert_eq!(ram.values()[3], 42);
+    assert_eq!(ram.address(), Some(3));
+    assert_eq!(bus.view(1).read(LEFT), Some(42));
+
+    ram.step(&mut bus.view(0));
+    ram.sync(&mut bus.view(0));
+    bus.commit();
+    assert_eq!(ram.address(), None);
+    assert_eq!(bus.view(1).read(LEFT), None);
+
+    // Negative addresses wrap around.
+    bus.view(1).write(LEFT, -1);
+    bus.commit();
+    ram.step(&mut bus.view(0));
+    ram.sync(&mut bus.view(0));
+    assert_eq!(ram.address(), Some(RAM_SIZE - 1));
+}
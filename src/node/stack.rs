@@ -1,13 +1,22 @@
-use super::Node;
+use std::hash::{Hash, Hasher};
+use super::{Node, WRITE_ORDER};
 use io::IoBusView;
-use core::Port::*;
 
-/// A node which stores values written to it on a stack. When the node is read from it will pop the
-/// top value off of the stack and return it.
+/// The number of values that a `StackMemoryNode` can hold.
+pub const STACK_CAPACITY: usize = 15;
+
+/// A node which stores values written to it on a stack, like the T30 memory node from the game.
+/// When the node is read from it will pop the top value off of the stack and return it.
+///
+/// The stack holds at most `STACK_CAPACITY` values. Once it is full, nodes that write to it will
+/// stall until the cycle after a value has been read. Values that are written by several neighbors
+/// in the same cycle are pushed in the order left, right, up, down. Reads are served before writes:
+/// a read always returns the value that was on top of the stack at the end of the previous cycle.
 #[derive(Debug)]
 pub struct StackMemoryNode {
     stack: Vec<isize>,
-    read_index: Option<usize>,
+    offer: Option<isize>,
+    base: usize,
 }
 
 impl StackMemoryNode {
@@ -15,40 +24,238 @@ impl StackMemoryNode {
     pub fn new() -> StackMemoryNode {
         StackMemoryNode {
             stack: Vec::new(),
-            read_index: None,
+            offer: None,
+            base: 0,
+        }
+    }
+
+    /// Get the values on the stack, in the order that they will be read.
+    pub fn values(&self) -> Vec<isize> {
+        let mut values = self.stack.clone();
+        if let Some(val) = self.offer {
+            values.insert(self.base, val);
         }
+
+        values.reverse();
+        values
+    }
+
+    /// Get the number of values on the stack.
+    pub fn len(&self) -> usize {
+        self.stack.len() + self.offer.iter().count()
+    }
+
+    /// Check if the stack is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Check if the stack is full. Writes to a full stack stall until a value is read.
+    pub fn is_full(&self) -> bool {
+        self.len() >= STACK_CAPACITY
     }
 }
 
 impl Node for StackMemoryNode {
-    /// At the start of each cycle, the top value is made available on all ports. Any values that
-    /// have been written to this node are then added to the stack.
+    /// Any values that have been written to this node are added to the stack, as long as there is
+    /// room for them. Values that don't fit are left on the bus, so their writers stay blocked.
     fn step(&mut self, io: &mut IoBusView) {
-        let dirs = vec![LEFT, RIGHT, UP, DOWN];
-
-        // Use last instead of pop so that the value is only removed if a node reads it.
-        if let Some(&val) = self.stack.last() {
-            self.read_index = Some(self.stack.len() - 1);
-            for &dir in dirs.iter() {
-                io.write(dir, val);
+        for &dir in WRITE_ORDER.iter() {
+            if self.is_full() {
+                break;
             }
-        }
 
-        for &dir in dirs.iter() {
             if let Some(val) = io.read(dir) {
                 self.stack.push(val);
             }
         }
     }
 
-    // At the end of each cycle, check if the top value was actually read from and clear it from
-    // the stack if it was.
+    /// At the end of each cycle, check if the offered value was read and make the top of the
+    /// stack available on all ports. Since every node has stepped by now, nothing can read the
+    /// offered value before the next cycle.
     fn sync(&mut self, io: &mut IoBusView) {
-        if !io.is_blocked() {
-            if let Some(index) = self.read_index {
-                self.stack.remove(index);
-                self.read_index = None;
+        if self.offer.is_some() && !io.is_blocked() {
+            self.offer = None;
+        }
+
+        // Values that were pushed while another value was being offered belong on top of it, so
+        // the offered value is put back underneath them.
+        if self.stack.len() > self.base {
+            if let Some(val) = self.offer.take() {
+                self.stack.insert(self.base, val);
+            }
+        }
+
+        if self.offer.is_some() {
+            return;
+        }
+
+        if let Some(val) = self.stack.pop() {
+            for &dir in WRITE_ORDER.iter() {
+                io.write(dir, val);
             }
+            self.offer = Some(val);
         }
+
+        self.base = self.stack.len();
+    }
+
+    fn hash_state(&self, mut state: &mut Hasher) -> bool {
+        self.stack.hash(&mut state);
+        self.offer.hash(&mut state);
+        self.base.hash(&mut state);
+        true
+    }
+}
+
+/// Run one cycle of a stack connected to the IoBus as node 0.
+#[cfg(test)]
+fn cycle(stack: &mut StackMemoryNode, bus: &mut ::io::IoBus) {
+    stack.step(&mut bus.view(0));
+    stack.sync(&mut bus.view(0));
+    bus.commit();
+}
+
+#[test]
+fn test_stack_order() {
+    use io::IoBus;
+    use core::Port::*;
+
+    let mut bus = IoBus::new();
+    bus.connect_full(0, 1, RIGHT);
+    let mut stack = StackMemoryNode::new();
+
+    for &val in [1, 2, 3].iter() {
+        bus.view(1).write(LEFT, val);
+        bus.commit();
+        cycle(&mut stack, &mut bus);
+    }
+
+    assert_eq!(stack.values(), vec![3, 2, 1]);
+
+    // A value pushed in the same cycle as a read is not returned until the next read.
+    assert_eq!(bus.view(1).read(LEFT), Some(3));
+    bus.view(1).write(LEFT, 4);
+    bus.commit();
+    cycle(&mut stack, &mut bus);
+    assert_eq!(stack.values(), vec![4, 2, 1]);
+
+    for &val in [4, 2, 1].iter() {
+        assert_eq!(bus.view(1).read(LEFT), Some(val));
+        cycle(&mut stack, &mut bus);
     }
+
+    assert!(stack.is_empty());
+    assert_eq!(bus.view(1).read(LEFT), None);
+}
+
+#[test]
+fn test_stack_capacity() {
+    use io::IoBus;
+    use core::Port::*;
+
+    let mut bus = IoBus::new();
+    bus.connect_full(0, 1, RIGHT);
+    let mut stack = StackMemoryNode::new();
+
+    for val in 0..(STACK_CAPACITY as isize + 1) {
+        bus.view(1).write(LEFT, val);
+        bus.commit();
+        cycle(&mut stack, &mut bus);
+    }
+
+    // The last write stalls until there is room for it.
+    assert!(stack.is_full());
+    assert_eq!(stack.values()[0], STACK_CAPACITY as isize - 1);
+    assert!(bus.view(1).is_blocked());
+
+    // Reading a value makes room for the stalled write in the next cycle.
+    assert_eq!(bus.view(1).read(LEFT), Some(STACK_CAPACITY as isize - 1));
+    cycle(&mut stack, &mut bus);
+    assert!(bus.view(1).is_blocked());
+    cycle(&mut stack, &mut bus);
+    assert!(!bus.view(1).is_blocked());
+    assert_eq!(stack.values()[0], STACK_CAPACITY as isize);
+    assert_eq!(stack.len(), STACK_CAPACITY);
+}
+
+#[test]
+fn test_stack_simultaneous_writes() {
+    use io::IoBus;
+    use core::Port::*;
+
+    let mut bus = IoBus::new();
+    bus.connect_full(0, 1, RIGHT)
+       .connect_full(0, 2, DOWN)
+       .connect_full(3, 0, RIGHT);
+    let mut stack = StackMemoryNode::new();
+
+    bus.view(1).write(LEFT, 1);
+    bus.view(2).write(UP, 2);
+    bus.view(3).write(RIGHT, 3);
+    bus.commit();
+    cycle(&mut stack, &mut bus);
+
+    // Values are pushed from the left, right, up and down ports in that order.
+    assert_eq!(stack.values(), vec![2, 1, 3]);
+
+    // Only one neighbor can read each value.
+    assert_eq!(bus.view(3).read(RIGHT), Some(2));
+    assert_eq!(bus.view(1).read(LEFT), None);
+    cycle(&mut stack, &mut bus);
+    assert_eq!(bus.view(1).read(LEFT), Some(1));
+}
+
+#[test]
+fn test_sequence_reverser() {
+    use save::{parse_save, Save};
+    use spec::{SpecBuilder, Tile};
+    use machine::{Puzzle, Outcome};
+
+    let save = parse_save("@1\nMOV UP DOWN\n\
+                           @5\nS: MOV UP ACC\nJEZ P\nMOV ACC LEFT\nSWP\nADD 1\nSWP\nJMP S\n\
+                           P: SWP\nL: JEZ E\nMOV LEFT RIGHT\nSUB 1\nJMP L\nE: SWP\nMOV 0 RIGHT\n\
+                           @6\nMOV LEFT DOWN\n\
+                           @10\nMOV UP DOWN\n").unwrap();
+
+    // Sequences end with a 0, which stays at the end of the reversed sequence.
+    let spec = SpecBuilder::new()
+        .tile(4, Tile::Memory)
+        .tile(7, Tile::Memory)
+        .input("IN", 1, vec![3, 1, 2, 0, 5, 0, 0, 9, 8, 7, 6, 0])
+        .output("OUT", 2, vec![2, 1, 3, 0, 5, 0, 0, 6, 7, 8, 9, 0])
+        .build(Save::new()).ok().unwrap();
+
+    let mut puzzle = Puzzle::new(&spec, &save, 0).ok().unwrap();
+    assert_eq!(puzzle.run(), Outcome::Passed);
+}
+
+#[test]
+fn test_signal_multiplier() {
+    use save::{parse_save, Save};
+    use spec::{SpecBuilder, Tile};
+    use machine::{Puzzle, Outcome};
+
+    // Node 5 pushes IN.A onto the stack IN.B times, and node 8 pops and adds that many values.
+    let save = parse_save("@1\nMOV UP DOWN\n\
+                           @2\nMOV UP DOWN\n\
+                           @5\nS: MOV UP ACC\nSAV\nMOV RIGHT ACC\nMOV ACC DOWN\n\
+                           L: JEZ S\nSUB 1\nSWP\nMOV ACC LEFT\nSWP\nJMP L\n\
+                           @6\nMOV UP LEFT\n\
+                           @8\nMOV 0 ACC\nSAV\nMOV RIGHT ACC\n\
+                           L: JEZ E\nSUB 1\nSWP\nADD UP\nSWP\nJMP L\nE: SWP\nMOV ACC RIGHT\n\
+                           @9\nMOV UP LEFT\nMOV LEFT RIGHT\n\
+                           @10\nMOV LEFT DOWN\n").unwrap();
+
+    let spec = SpecBuilder::new()
+        .tile(4, Tile::Memory)
+        .tile(7, Tile::Memory)
+        .input("IN.A", 1, vec![3, 0, 9, 2, 7, 1])
+        .input("IN.B", 2, vec![4, 5, 9, 0, 1, 8])
+        .output("OUT", 2, vec![12, 0, 81, 0, 7, 8])
+        .build(Save::new()).ok().unwrap();
+
+    let mut puzzle = Puzzle::new(&spec, &save, 0).ok().unwrap();
+    assert_eq!(puzzle.run(), Outcome::Passed);
 }
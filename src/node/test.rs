@@ -1,6 +1,8 @@
 use std::collections::LinkedList;
-use super::{Node, TestNode, TestState};
+use std::hash::{Hash, Hasher};
+use super::{Node, TestNode, TestState, TestReport, IoAction};
 use super::TestState::*;
+use core::Port;
 use core::Port::*;
 use image::Image;
 use io::IoBusView;
@@ -36,45 +38,78 @@ impl Node for TestInputNode {
             self.blocked = false;
         }
     }
+
+    fn blocked_on(&self) -> Option<(IoAction, Vec<Port>)> {
+        if self.blocked {
+            Some((IoAction::Write, vec![DOWN]))
+        } else {
+            None
+        }
+    }
+
+    fn hash_state(&self, mut state: &mut Hasher) -> bool {
+        self.test_data.len().hash(&mut state);
+        self.blocked.hash(&mut state);
+        true
+    }
 }
 
+/// Checks the values that are written to an output against the expected values. The test fails as
+/// soon as a value doesn't match, or if a value is written after all of the expected values have
+/// been received.
 #[derive(Debug)]
 pub struct TestOutputNode {
-    test_data: LinkedList<isize>,
-    results: Vec<(isize, isize)>,
+    expected: Vec<isize>,
+    received: Vec<isize>,
 }
 
 impl TestOutputNode {
     pub fn with_data(test_data: &Vec<isize>) -> TestOutputNode {
         TestOutputNode {
-            test_data: test_data.iter().map(|&i| i).collect::<LinkedList<_>>(),
-            results: Vec::new(),
+            expected: test_data.clone(),
+            received: Vec::new(),
         }
     }
+
+    /// Get the index of the first received value that doesn't match, if there is one.
+    fn mismatch(&self) -> Option<usize> {
+        self.received.iter()
+            .enumerate()
+            .position(|(i, val)| self.expected.get(i) != Some(val))
+    }
 }
 
 impl Node for TestOutputNode {
     fn step(&mut self, io: &mut IoBusView) {
         if let Some(val) = io.read(UP) {
-            if let Some(expected) = self.test_data.pop_front() {
-                self.results.push((expected, val));
-            }
+            self.received.push(val);
         }
     }
+
+    fn hash_state(&self, mut state: &mut Hasher) -> bool {
+        self.received.hash(&mut state);
+        true
+    }
 }
 
 impl TestNode for TestOutputNode {
     fn state(&self) -> TestState {
-        if !self.test_data.is_empty() {
+        if self.mismatch().is_some() {
+            Failed
+        } else if self.received.len() < self.expected.len() {
             Testing
         } else {
-            if self.results.iter().all(|&(e, a)| e == a) {
-                Passed
-            } else {
-                Failed
-            }
+            Passed
         }
     }
+
+    fn report(&self) -> Option<TestReport> {
+        Some(TestReport::Output {
+            expected: self.expected.clone(),
+            received: self.received.clone(),
+            mismatch: self.mismatch(),
+        })
+    }
 }
 
 #[derive(Debug)]
@@ -98,6 +133,11 @@ impl Node for TestImageNode {
             self.image.write(val);
         }
     }
+
+    fn hash_state(&self, state: &mut Hasher) -> bool {
+        self.image.hash_state(state);
+        true
+    }
 }
 
 impl TestNode for TestImageNode {
@@ -108,4 +148,48 @@ impl TestNode for TestImageNode {
             Testing
         }
     }
+
+    fn report(&self) -> Option<TestReport> {
+        Some(TestReport::Image {
+            width: self.image.width(),
+            height: self.image.height(),
+            mismatches: self.test_image.diff(&self.image),
+        })
+    }
+}
+
+#[test]
+fn test_output_node() {
+    use io::IoBus;
+
+    let mut bus = IoBus::new();
+    bus.connect_half(0, 1, DOWN);
+
+    let mut node = TestOutputNode::with_data(&vec![1, 2, 3]);
+    for &val in [1, 5].iter() {
+        bus.view(0).write(DOWN, val);
+        bus.commit();
+        node.step(&mut bus.view(1));
+    }
+
+    // A mismatch fails before the remaining values have arrived.
+    assert_eq!(node.state(), Failed);
+    assert_eq!(node.report(), Some(TestReport::Output {
+        expected: vec![1, 2, 3],
+        received: vec![1, 5],
+        mismatch: Some(1),
+    }));
+
+    let mut node = TestOutputNode::with_data(&vec![1]);
+    bus.view(0).write(DOWN, 1);
+    bus.commit();
+    node.step(&mut bus.view(1));
+    assert_eq!(node.state(), Passed);
+
+    // Values after the end of the expected stream are failures too.
+    bus.view(0).write(DOWN, 1);
+    bus.commit();
+    node.step(&mut bus.view(1));
+    assert_eq!(node.state(), Failed);
+    assert_eq!(node.mismatch(), Some(1));
 }
@@ -5,7 +5,7 @@ use std::fmt::{Display, Formatter, Error};
 use std::collections::HashMap;
 use core::*;
 use core::Instruction::*;
-use lex::{lex_program, Label, Line};
+use lex::{lex_program, Label, Line, Span};
 
 /// An error that can be returned while parsing a TIS-100 assembly program.
 #[derive(Debug, PartialEq)]
@@ -35,7 +35,8 @@ impl Display for ParseProgramError {
     }
 }
 
-/// All errors discovered while parsing a TIS-100 assembly program.
+/// All errors discovered while parsing a TIS-100 assembly program, with the line number of each
+/// error. `error_spans` finds where on its line each error is.
 pub type ProgramErrors = Vec<(usize, ParseProgramError)>;
 
 use self::ParseProgramError::*;
@@ -75,8 +76,8 @@ pub fn parse_program(src: &str) -> Result<Program, ProgramErrors> {
     let lines = lex_program(src);
 
     // Lable mapping pass
-    for &Line(line_num, ref maybe_label, _) in lines.iter() {
-        if let &Some(Label(ref name, index)) = maybe_label {
+    for &Line(line_num, ref maybe_label, _, _) in lines.iter() {
+        if let &Some(Label(ref name, index, _)) = maybe_label {
             if name.len() == 0 {
                 errors.push((line_num, InvalidLabel));
             } else if let None = label_map.get(name) {
@@ -88,7 +89,7 @@ pub fn parse_program(src: &str) -> Result<Program, ProgramErrors> {
     }
 
     // Instruction pass
-    for &Line(line_num, _, ref lexemes) in lines.iter() {
+    for &Line(line_num, _, ref lexemes, _) in lines.iter() {
         if lexemes.len() > 0 {
             match parse_instruction(&lexemes[0], &lexemes[1..], &label_map) {
                 Ok(instruction) => instructions.push(instruction),
@@ -104,6 +105,51 @@ pub fn parse_program(src: &str) -> Result<Program, ProgramErrors> {
     }
 }
 
+/// Find the span of the label or lexemes that each error refers to, in the same order as the errors.
+/// The errors must be the ones that `parse_program` returned for the same source.
+///
+/// # Example
+///
+/// ```
+/// use tis_100::parse::{parse_program, error_spans};
+///
+/// let src = "MOV UP ACX\n";
+/// let errors = parse_program(src).unwrap_err();
+/// let span = error_spans(src, &errors)[0];
+/// assert_eq!(&src[span.byte_start..span.byte_end], "ACX");
+/// ```
+pub fn error_spans(src: &str, errors: &ProgramErrors) -> Vec<Span> {
+    let lines = lex_program(src);
+
+    errors.iter().map(|&(line_num, ref error)| {
+        let Line(_, ref label, _, ref spans) = lines[line_num];
+        match (error, label) {
+            (&InvalidLabel, &Some(Label(_, _, span))) => span,
+            (&DuplicateLabel(_), &Some(Label(_, _, span))) => span,
+            _ => error_span(error, spans),
+        }
+    }).collect()
+}
+
+/// Find the span of the lexemes of an instruction that an error refers to. A missing operand is
+/// spanned by the empty span after the last lexeme.
+fn error_span(error: &ParseProgramError, spans: &[Span]) -> Span {
+    let last = spans.len() - 1;
+    let (first, end) = match *error {
+        InvalidOpcode(_) => (0, 0),
+        InvalidExpression(_) | UndefinedLabel(_) => (1, 1),
+        InvalidRegister(_) => (2, 2),
+        TooManyOperands(ref operands) => (spans.len().saturating_sub(operands.split(' ').count()), last),
+        MissingOperand(_) => {
+            let end = spans[last];
+            return Span { start: end.end, byte_start: end.byte_end, ..end };
+        },
+        _ => (0, last),
+    };
+
+    spans[first.min(last)].to(spans[end.min(last)])
+}
+
 /// Attempt to parse a single TIS-100 assembly instruction.
 fn parse_instruction(opcode: &str, operands: &[String], labels: &HashMap<String, isize>) -> ParseResult<Instruction> {
     match str::parse::<Opcode>(opcode) {
@@ -227,6 +273,18 @@ impl FromStr for Opcode {
     }
 }
 
+#[test]
+fn test_parse_error_spans() {
+    let src = "MOV UP ACX\nFOO 1\nL: ADD\n:\nJMP NOWHERE\nNEG 1 2\nL: NOP\n";
+    let errors = parse_program(src).unwrap_err();
+    let spans = errors.iter().zip(error_spans(src, &errors))
+        .map(|(&(line, _), span)| (line, span.start, span.end))
+        .collect::<Vec<_>>();
+    assert_eq!(spans, vec![(3, 0, 1), (6, 0, 1), (0, 7, 10), (1, 0, 3), (2, 6, 6), (4, 4, 11), (5, 4, 7)]);
+    assert_eq!(errors[0].1, InvalidLabel);
+    assert_eq!(errors[1].1, DuplicateLabel("L".to_string()));
+}
+
 #[test]
 fn test_parse_opcode() {
     assert_eq!(str::parse::<Opcode>("NOP"), Ok(NOP));
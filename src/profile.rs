@@ -0,0 +1,195 @@
+//! Profiling of TIS-100 systems.
+//!
+//! A `Profiler` is attached to a `Tis100` with `Tis100::set_profiling`. While it is attached, it
+//! counts the cycles that each execution node spends in each `Mode`, how many times each
+//! instruction completes, and how many values are passed over each connection between nodes. The
+//! profiler only observes the system, so it never changes how a program executes.
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Error};
+use vec_map::VecMap;
+use io::NodeId;
+use machine::node_name;
+use node::Mode;
+
+/// The activity of a single execution node.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct NodeProfile {
+    /// The node that was profiled.
+    pub node: NodeId,
+    /// The number of cycles in which the node executed an instruction.
+    pub run: usize,
+    /// The number of cycles in which the node was waiting to read a value.
+    pub read: usize,
+    /// The number of cycles in which the node was waiting for a value that it wrote to be read.
+    pub write: usize,
+    /// The number of cycles in which the node had nothing to execute.
+    pub idle: usize,
+    /// The number of times that each instruction completed, by its index in the program. Any
+    /// instructions after the last one that completed are missing.
+    pub instructions: Vec<usize>,
+}
+
+impl NodeProfile {
+    fn new(node: NodeId) -> NodeProfile {
+        NodeProfile {
+            node: node,
+            run: 0,
+            read: 0,
+            write: 0,
+            idle: 0,
+            instructions: Vec::new(),
+        }
+    }
+
+    /// The percentage of cycles in which the node executed an instruction.
+    pub fn utilization(&self) -> usize {
+        let cycles = self.run + self.read + self.write + self.idle;
+        if cycles == 0 {
+            0
+        } else {
+            self.run * 100 / cycles
+        }
+    }
+}
+
+/// The number of values that were passed from one node to another.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct EdgeProfile {
+    pub from: NodeId,
+    pub to: NodeId,
+    pub values: usize,
+}
+
+/// The results of profiling a system. When it is displayed, nodes that were idle for every cycle
+/// are left out, since they have no program.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct Profile {
+    /// The number of cycles that were profiled.
+    pub cycles: usize,
+    /// The activity of each execution node, in order of the nodes.
+    pub nodes: Vec<NodeProfile>,
+    /// Every connection that passed at least one value, busiest first.
+    pub edges: Vec<EdgeProfile>,
+}
+
+impl Display for Profile {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        writeln!(f, "PROFILE OF {} CYCLES", self.cycles)?;
+        writeln!(f, "{:<8} {:>6} {:>6} {:>6} {:>6} {:>5}", "", "RUN", "READ", "WRTE", "IDLE", "UTIL")?;
+
+        for node in self.nodes.iter().filter(|node| node.idle < self.cycles) {
+            writeln!(f, "{:<8} {:>6} {:>6} {:>6} {:>6} {:>4}%", node_name(node.node), node.run, node.read,
+                     node.write, node.idle, node.utilization())?;
+        }
+
+        for node in self.nodes.iter().filter(|node| !node.instructions.is_empty()) {
+            writeln!(f)?;
+            writeln!(f, "{} INSTRUCTIONS", node_name(node.node))?;
+
+            for (index, count) in node.instructions.iter().enumerate() {
+                writeln!(f, "{:>5} {:>9}", index, count)?;
+            }
+        }
+
+        if !self.edges.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "BUSIEST CONNECTIONS")?;
+
+            for edge in self.edges.iter() {
+                writeln!(f, "{} -> {}: {} VALUES", node_name(edge.from), node_name(edge.to), edge.values)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Collects a `Profile` while a system executes.
+#[derive(Debug)]
+pub struct Profiler {
+    cycles: usize,
+    nodes: VecMap<NodeProfile>,
+    edges: HashMap<(NodeId, NodeId), usize>,
+}
+
+impl Profiler {
+    /// Construct a new `Profiler` that hasn't seen any cycles.
+    pub fn new() -> Profiler {
+        Profiler {
+            cycles: 0,
+            nodes: VecMap::new(),
+            edges: HashMap::new(),
+        }
+    }
+
+    /// Get the profile of a node, creating it if this is the first time that it was seen.
+    fn node(&mut self, node: NodeId) -> &mut NodeProfile {
+        self.nodes.entry(node).or_insert_with(|| NodeProfile::new(node))
+    }
+
+    /// Record the mode that a node was in after it stepped.
+    pub fn record_mode(&mut self, node: NodeId, mode: Mode) {
+        let profile = self.node(node);
+        match mode {
+            Mode::Run => profile.run += 1,
+            Mode::Read => profile.read += 1,
+            Mode::Wrte => profile.write += 1,
+            Mode::Idle => profile.idle += 1,
+        }
+    }
+
+    /// Record that the instruction at `pc` completed on a node.
+    pub fn record_instruction(&mut self, node: NodeId, pc: usize) {
+        let profile = self.node(node);
+        if profile.instructions.len() <= pc {
+            profile.instructions.resize(pc + 1, 0);
+        }
+        profile.instructions[pc] += 1;
+    }
+
+    /// Record that a value was passed from one node to another.
+    pub fn record_transfer(&mut self, from: NodeId, to: NodeId) {
+        *self.edges.entry((from, to)).or_insert(0) += 1;
+    }
+
+    /// Record the end of a cycle.
+    pub fn record_cycle(&mut self) {
+        self.cycles += 1;
+    }
+
+    /// Get the profile of every cycle that has been recorded so far.
+    pub fn profile(&self) -> Profile {
+        let mut edges = self.edges.iter()
+            .map(|(&(from, to), &values)| EdgeProfile { from: from, to: to, values: values })
+            .collect::<Vec<_>>();
+        edges.sort_by(|a, b| b.values.cmp(&a.values).then((a.from, a.to).cmp(&(b.from, b.to))));
+
+        Profile {
+            cycles: self.cycles,
+            nodes: self.nodes.values().cloned().collect(),
+            edges: edges,
+        }
+    }
+}
+
+#[test]
+fn test_profiler() {
+    let mut profiler = Profiler::new();
+    profiler.record_mode(1, Mode::Run);
+    profiler.record_instruction(1, 2);
+    profiler.record_mode(1, Mode::Read);
+    profiler.record_mode(0, Mode::Idle);
+    profiler.record_transfer(1, 5);
+    profiler.record_transfer(0, 1);
+    profiler.record_transfer(1, 5);
+    profiler.record_cycle();
+
+    let profile = profiler.profile();
+    assert_eq!(profile.cycles, 1);
+    assert_eq!(profile.nodes.iter().map(|node| node.node).collect::<Vec<_>>(), vec![0, 1]);
+    assert_eq!(profile.nodes[1].instructions, vec![0, 0, 1]);
+    assert_eq!(profile.nodes[1].utilization(), 50);
+    assert_eq!(profile.edges[0], EdgeProfile { from: 1, to: 5, values: 2 });
+    assert_eq!(profile.edges[1], EdgeProfile { from: 0, to: 1, values: 1 });
+}
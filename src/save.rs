@@ -4,7 +4,9 @@ use vec_map::VecMap;
 use std::io::Read;
 use std::fs::File;
 use core::Program;
+use lex::{lex_program, Line};
 use parse::{parse_program, ProgramErrors};
+use disasm::disassemble;
 
 /// Programs that are assigned to specific nodes in a TIS-100.
 pub type Save = VecMap<Program>;
@@ -75,6 +77,80 @@ pub fn parse_save(src: &str) -> Result<Save, SaveErrors> {
     }
 }
 
+/// Write a save in the format of the game's save files, with a header for each node followed by
+/// its program and a blank line. Labels are rebuilt by `disassemble`, so the text parses back
+/// into the same `Save`, unless a program needs more lines than the lexer reads once its labels
+/// are added.
+///
+/// # Example
+///
+/// ```
+/// use tis_100::save::{parse_save, write_save};
+///
+/// let save = parse_save("@0\n@1\nL: ADD 1\nJMP L\n").unwrap();
+/// let src = write_save(&save);
+/// assert_eq!(src, "@0\n\n@1\nA: ADD 1\nJMP A\n\n");
+/// assert_eq!(parse_save(&src).unwrap(), save);
+/// ```
+pub fn write_save(save: &Save) -> String {
+    let mut src = String::new();
+
+    for (node, prog) in save.iter() {
+        src.push_str(&format!("@{}\n{}\n", node, disassemble(prog)));
+    }
+
+    src
+}
+
+/// Count the instructions in every program in the save, the way the game scores solutions.
+pub fn instruction_count(save: &Save) -> usize {
+    save.values().map(|prog| prog.len()).sum()
+}
+
+/// Count the nodes that have at least one instruction.
+pub fn node_count(save: &Save) -> usize {
+    save.values().filter(|prog| !prog.is_empty()).count()
+}
+
+/// Split a save file into the nodes that it contains. Each node has its number, the line number of
+/// its `@` header, and its source.
+pub fn split_nodes(src: &str) -> Vec<(usize, usize, String)> {
+    let mut nodes: Vec<(usize, usize, String)> = Vec::new();
+
+    for (index, line) in src.lines().enumerate() {
+        let header = line.trim();
+        if header.starts_with("@") {
+            if let Ok(num) = header[1..].parse::<usize>() {
+                nodes.push((num, index + 1, String::new()));
+                continue;
+            }
+        }
+
+        if let Some(&mut (_, _, ref mut node_src)) = nodes.last_mut() {
+            node_src.push_str(line);
+            node_src.push('\n');
+        }
+    }
+
+    nodes
+}
+
+/// Find the line of the save file that each instruction was parsed from. Each node maps to the
+/// line numbers of its instructions, in order, starting from 1 for the first line of the file.
+pub fn instruction_lines(src: &str) -> VecMap<Vec<usize>> {
+    let mut lines = VecMap::new();
+
+    for (node, header, node_src) in split_nodes(src) {
+        let node_lines = lex_program(&node_src).into_iter()
+            .filter(|&Line(_, _, ref words, _)| !words.is_empty())
+            .map(|Line(index, _, _, _)| header + 1 + index)
+            .collect();
+        lines.insert(node, node_lines);
+    }
+
+    lines
+}
+
 /// Pretty print errors from parsing a save file.
 pub fn pretty_print_errors(save_errors: SaveErrors) {
     for (node_num, ref errors) in save_errors.iter() {
@@ -89,3 +165,25 @@ fn test_parse_save() {
     let save = parse_save("@1\nADD 1\n@2\nADD 1\n").unwrap();
     assert_eq!(save.len(), 2);
 }
+
+#[test]
+fn test_write_save() {
+    let src = "@0\n\n@3\nSTART: MOV UP ACC # read\nJGZ POS\nJMP START\nPOS: MOV ACC DOWN\n\n@10\nMOV ANY LAST\n";
+    let save = parse_save(src).unwrap();
+    assert_eq!(parse_save(&write_save(&save)).unwrap(), save);
+}
+
+#[test]
+fn test_instruction_lines() {
+    let lines = instruction_lines("@0\n\n@1\nADD 1\n# Comment\nL:\nL2: SUB 1\n\n@2\nNOP\n");
+    assert_eq!(lines.get(0), Some(&vec![]));
+    assert_eq!(lines.get(1), Some(&vec![4, 7]));
+    assert_eq!(lines.get(2), Some(&vec![10]));
+}
+
+#[test]
+fn test_save_counts() {
+    let save = parse_save("@0\n@1\nADD 1\nL: SUB 1\n@2\nJMP L\nL: NOP\n").unwrap();
+    assert_eq!(instruction_count(&save), 4);
+    assert_eq!(node_count(&save), 2);
+}
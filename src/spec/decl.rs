@@ -0,0 +1,151 @@
+//! Loading and writing specs in the declarative TOML and JSON formats.
+
+use serde_json;
+use toml;
+use save::Save;
+use machine::NUM_NODES;
+use super::{Spec, Stream, Tile, SpecError, Registry};
+use super::SpecError::*;
+
+/// The contents of a declarative spec file.
+#[derive(Debug, Serialize, Deserialize)]
+struct Definition {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    description: Vec<String>,
+    layout: Vec<Tile>,
+    #[serde(default)]
+    streams: Vec<Stream>,
+}
+
+impl Definition {
+    /// Capture the puzzle definition from a `Spec`.
+    fn from_spec(spec: &Spec) -> Definition {
+        Definition {
+            name: spec.name.clone(),
+            description: spec.description.clone(),
+            layout: spec.layout.clone(),
+            streams: spec.streams.clone(),
+        }
+    }
+
+    /// Combine the puzzle definition with a save and a registry to create a `Spec`.
+    fn into_spec(self, save: Save, registry: Registry) -> Result<Spec, SpecError> {
+        if self.layout.len() != NUM_NODES {
+            return Err(GetLayoutFailed);
+        }
+
+        Ok(Spec {
+            save: save,
+            name: self.name,
+            description: self.description,
+            layout: self.layout,
+            streams: self.streams,
+            seed: None,
+            generator: None,
+            registry: registry,
+        })
+    }
+}
+
+/// Load a `Spec` from the source of a TOML file.
+pub fn from_toml(src: &str, save: Save, registry: Registry) -> Result<Spec, SpecError> {
+    match toml::from_str::<Definition>(src) {
+        Ok(definition) => definition.into_spec(save, registry),
+        Err(_) => Err(DecodeFailed),
+    }
+}
+
+/// Load a `Spec` from the source of a JSON file.
+pub fn from_json(src: &str, save: Save, registry: Registry) -> Result<Spec, SpecError> {
+    match serde_json::from_str::<Definition>(src) {
+        Ok(definition) => definition.into_spec(save, registry),
+        Err(_) => Err(DecodeFailed),
+    }
+}
+
+/// Convert a `Spec` to the source of a TOML file.
+pub fn to_toml(spec: &Spec) -> Result<String, SpecError> {
+    toml::to_string(&Definition::from_spec(spec)).map_err(|_| EncodeFailed)
+}
+
+/// Convert a `Spec` to the source of a JSON file.
+pub fn to_json(spec: &Spec) -> Result<String, SpecError> {
+    serde_json::to_string_pretty(&Definition::from_spec(spec)).map_err(|_| EncodeFailed)
+}
+
+#[test]
+fn test_toml_round_trip() {
+    let src = "name = \"TEST\"\n\
+               layout = [\"compute\", \"compute\", \"compute\", \"compute\",\n\
+                         \"memory\", \"compute\", \"compute\", \"compute\",\n\
+                         \"compute\", \"compute\", \"compute\", \"damaged\"]\n\
+               [[streams]]\n\
+               kind = \"input\"\n\
+               name = \"IN.A\"\n\
+               node = 1\n\
+               data = [1, -2, 3]\n";
+
+    let spec = from_toml(src, Save::new(), Registry::new()).ok().unwrap();
+    assert_eq!(spec.name, "TEST");
+    assert_eq!(spec.layout[4], Tile::Memory);
+    assert_eq!(spec.layout[11], Tile::Damaged);
+    assert_eq!(spec.streams[0].data, vec![1, -2, 3]);
+
+    let spec = from_toml(&to_toml(&spec).ok().unwrap(), Save::new(), Registry::new()).ok().unwrap();
+    assert_eq!(spec.name, "TEST");
+    assert_eq!(spec.layout[4], Tile::Memory);
+    assert_eq!(spec.streams[0].name, "IN.A");
+    assert_eq!(spec.streams[0].data, vec![1, -2, 3]);
+}
+
+#[test]
+fn test_json_round_trip() {
+    let src = "{\"layout\": [\"compute\", \"compute\", \"compute\", \"compute\",\
+                             \"compute\", \"compute\", \"compute\", \"compute\",\
+                             \"compute\", \"compute\", \"compute\", \"compute\"],\
+                \"streams\": [{\"kind\": \"output\", \"name\": \"OUT.A\", \"node\": 2, \"data\": [4]}]}";
+
+    let spec = from_json(src, Save::new(), Registry::new()).ok().unwrap();
+    assert_eq!(spec.streams[0].node, 2);
+
+    let spec = from_json(&to_json(&spec).ok().unwrap(), Save::new(), Registry::new()).ok().unwrap();
+    assert_eq!(spec.name, "");
+    assert_eq!(spec.streams[0].name, "OUT.A");
+    assert_eq!(spec.streams[0].data, vec![4]);
+}
+
+#[test]
+fn test_invalid_layout() {
+    assert!(from_toml("layout = [\"compute\"]", Save::new(), Registry::new()).is_err());
+    assert!(from_json("{\"layout\": [\"bogus\"]}", Save::new(), Registry::new()).is_err());
+}
+
+#[test]
+fn test_custom_kinds() {
+    use node::{DamagedExecutionNode, TestOutputNode};
+    use super::StreamKind;
+
+    let src = "layout = [\"compute\", \"compute\", \"compute\", \"compute\",\n\
+                         \"compute\", \"BROKEN\", \"compute\", \"compute\",\n\
+                         \"compute\", \"compute\", \"compute\", \"compute\"]\n\
+               [[streams]]\n\
+               kind = \"EXACT\"\n\
+               name = \"OUT.A\"\n\
+               node = 1\n\
+               data = [1]\n";
+
+    let mut registry = Registry::new();
+    registry.register_tile("BROKEN", || Box::new(DamagedExecutionNode))
+            .register_test("EXACT", |s| Box::new(TestOutputNode::with_data(&s.data)));
+
+    let spec = from_toml(src, Save::new(), registry).ok().unwrap();
+    assert_eq!(spec.layout[5], Tile::Custom("BROKEN".to_string()));
+    assert_eq!(spec.streams[0].kind, StreamKind::Custom("EXACT".to_string()));
+    assert!(spec.check().is_ok());
+    assert_eq!(spec.tests().len(), 1);
+
+    let spec = from_toml(src, Save::new(), Registry::new()).ok().unwrap();
+    assert!(spec.check().is_err());
+}
@@ -0,0 +1,68 @@
+//! Specs for the campaign puzzles from the game.
+//!
+//! # Example
+//!
+//! ```
+//! use tis_100::spec::library;
+//!
+//! let segment = library::find("10981").unwrap();
+//! assert_eq!(segment.name, "SIGNAL AMPLIFIER");
+//! ```
+
+/// A campaign puzzle, identified by the game's segment ID.
+#[derive(Debug)]
+pub struct Segment {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub source: &'static str,
+}
+
+/// All of the campaign puzzles that are included with the emulator, in campaign order.
+pub static SEGMENTS: &'static [Segment] = &[
+    Segment { id: "00150", name: "SELF-TEST DIAGNOSTIC", source: include_str!("puzzles/00150.lua") },
+    Segment { id: "10981", name: "SIGNAL AMPLIFIER", source: include_str!("puzzles/10981.lua") },
+    Segment { id: "20176", name: "DIFFERENTIAL CONVERTER", source: include_str!("puzzles/20176.lua") },
+    Segment { id: "21340", name: "SIGNAL COMPARATOR", source: include_str!("puzzles/21340.lua") },
+    Segment { id: "22280", name: "SIGNAL MULTIPLEXER", source: include_str!("puzzles/22280.lua") },
+    Segment { id: "30647", name: "SEQUENCE GENERATOR", source: include_str!("puzzles/30647.lua") },
+    Segment { id: "31904", name: "SEQUENCE COUNTER", source: include_str!("puzzles/31904.lua") },
+    Segment { id: "32050", name: "SIGNAL EDGE DETECTOR", source: include_str!("puzzles/32050.lua") },
+    Segment { id: "33762", name: "INTERRUPT HANDLER", source: include_str!("puzzles/33762.lua") },
+    Segment { id: "40196", name: "SIGNAL PATTERN DETECTOR", source: include_str!("puzzles/40196.lua") },
+    Segment { id: "41427", name: "SEQUENCE PEAK DETECTOR", source: include_str!("puzzles/41427.lua") },
+    Segment { id: "42656", name: "SEQUENCE REVERSER", source: include_str!("puzzles/42656.lua") },
+    Segment { id: "43786", name: "SIGNAL MULTIPLIER", source: include_str!("puzzles/43786.lua") },
+    Segment { id: "50370", name: "IMAGE TEST PATTERN 1", source: include_str!("puzzles/50370.lua") },
+    Segment { id: "51781", name: "IMAGE TEST PATTERN 2", source: include_str!("puzzles/51781.lua") },
+    Segment { id: "52544", name: "EXPOSURE MASK VIEWER", source: include_str!("puzzles/52544.lua") },
+    Segment { id: "53897", name: "HISTOGRAM VIEWER", source: include_str!("puzzles/53897.lua") },
+    Segment { id: "60099", name: "SIGNAL WINDOW FILTER", source: include_str!("puzzles/60099.lua") },
+    Segment { id: "61212", name: "SIGNAL DIVIDER", source: include_str!("puzzles/61212.lua") },
+    Segment { id: "62711", name: "SEQUENCE INDEXER", source: include_str!("puzzles/62711.lua") },
+    Segment { id: "63534", name: "SEQUENCE SORTER", source: include_str!("puzzles/63534.lua") },
+    Segment { id: "70601", name: "STORED IMAGE DECODER", source: include_str!("puzzles/70601.lua") },
+];
+
+/// Find a campaign puzzle by its segment ID. The ID may optionally include the `SEGMENT` prefix
+/// used by the game, e.g. `"SEGMENT 10981"`.
+pub fn find(id: &str) -> Option<&'static Segment> {
+    let id = id.trim();
+    let id = match id.get(..7) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("SEGMENT") => id[7..].trim(),
+        _ => id,
+    };
+
+    SEGMENTS.iter().find(|s| s.id == id)
+}
+
+#[test]
+fn test_find() {
+    assert_eq!(find("00150").map(|s| s.name), Some("SELF-TEST DIAGNOSTIC"));
+    assert_eq!(find("SEGMENT 63534").map(|s| s.name), Some("SEQUENCE SORTER"));
+    assert_eq!(find("segment 70601").map(|s| s.name), Some("STORED IMAGE DECODER"));
+    assert!(find("99999").is_none());
+
+    // Only an ASCII prefix is removed, and input that isn't ASCII doesn't panic.
+    assert!(find("ſEGMENT 10981").is_none());
+    assert!(find("SEGMENé 10981").is_none());
+}
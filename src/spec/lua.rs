@@ -0,0 +1,959 @@
+//! Loading specs from Lua files.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::ptr;
+use std::rc::Rc;
+use std::cell::{Cell, RefCell};
+use std::marker::PhantomData;
+use std::time::{Duration, Instant};
+use libc::{self, c_void, size_t};
+use hlua::{self, Lua, LuaTable, LuaError};
+use hlua::functions_read::LuaFunction;
+use core::Port;
+use core::Port::*;
+use io::IoBusView;
+use node::Node;
+use save::Save;
+use machine::NUM_NODES;
+use super::{Spec, Stream, Tile, StreamKind, SpecError, Registry};
+use super::Tile::*;
+use super::StreamKind::*;
+use super::SpecError::*;
+
+/// Used to seed the Lua random number generator.
+const SEED_RANDOM_EXEC: &'static str = "math.randomseed(...)";
+
+/// The maximum number of Lua instructions that a spec may execute while it is being loaded.
+const MAX_INSTRUCTIONS: u32 = 50_000_000;
+
+/// The maximum number of seconds that a spec may take to load. This is wall-clock time rather than
+/// CPU time, so it also counts time that the process spends waiting to be scheduled.
+const MAX_SECONDS: u64 = 10;
+
+/// The maximum number of Lua instructions that a custom tile may execute in a single step or sync.
+const NODE_MAX_INSTRUCTIONS: u32 = 100_000;
+
+/// The maximum number of milliseconds of wall-clock time that a custom tile may take in a single
+/// step or sync.
+const NODE_MAX_MILLIS: u64 = 1000;
+
+/// The maximum number of bytes that the Lua context of a spec may allocate. The count hook can't
+/// interrupt a single call to a C function like `string.rep`, so memory is limited separately.
+const MAX_MEMORY: usize = 32 * 1024 * 1024;
+
+/// The number of instructions between checks of the execution budget.
+const BUDGET_INTERVAL: u32 = 1000;
+
+/// The error message raised when a spec exceeds its execution budget.
+const BUDGET_ERROR: &'static str = "spec exceeded its execution budget";
+
+/// The error message that Lua raises when an allocation fails.
+const MEMORY_ERROR: &'static str = "not enough memory";
+
+/// Defines `register_tile`, along with the functions used to create and run the nodes for custom
+/// tiles. The Rust functions that access the node's `IoBusView` are removed from the globals, so
+/// that they can only be used through the wrapper that is passed to the node. This runs before
+/// `SANDBOX_EXEC`, which hides the other functions from the spec.
+const TILES_EXEC: &'static str = "
+    local read, write, is_blocked = tis_read, tis_write, tis_is_blocked
+    tis_read, tis_write, tis_is_blocked = nil, nil, nil
+    local type, error = type, error
+    local factories, nodes = {}, {}
+
+    local function check_number(value)
+        if type(value) ~= 'number' then
+            error('expected a number')
+        end
+    end
+
+    local io = {
+        read = function(self, port)
+            check_number(port)
+            local ok, value = read(port)
+            if ok then
+                return value
+            end
+        end,
+        write = function(self, port, value)
+            check_number(port)
+            check_number(value)
+            write(port, value)
+        end,
+        is_blocked = function(self)
+            return is_blocked()
+        end,
+    }
+
+    function register_tile(name, factory)
+        if type(name) ~= 'string' or type(factory) ~= 'function' then
+            error('register_tile expects a name and a function')
+        end
+        factories[name] = factory
+        return name
+    end
+
+    function tis_has_tile(name)
+        return factories[name] ~= nil
+    end
+
+    function tis_create_node(id, name)
+        nodes[id] = factories[name]()
+    end
+
+    function tis_call_node(id, method)
+        local f = nodes[id][method]
+        if f then
+            f(io)
+        end
+    end
+";
+
+/// Restricts the Lua environment before the spec is executed. A count hook reports to the spec's
+/// `Budget` and raises an error once it has been used up, and every global except for the `math`,
+/// `string` and `table` libraries, `register_tile` and a handful of basic functions is removed.
+/// `pcall` and `xpcall` are not available, so that a spec can't catch the error raised by the hook.
+///
+/// The functions that are called from Rust are moved out of the globals and into the `__index` of
+/// their metatable, so that the spec can't replace them. `tis_execute` runs a chunk with the memory
+/// limit in place. Running it under `pcall` turns a failed allocation into an ordinary error, since
+/// hlua panics if it sees one.
+const SANDBOX_EXEC: &'static str = "
+    local G, sethook, error, pairs, spend, limit = _G, debug.sethook, error, pairs, tis_spend_budget, tis_limit_memory
+    local load, pcall, rawset, setmetatable = load, pcall, rawset, setmetatable
+    local interval, message = ...
+
+    sethook(function()
+        if not spend(interval) then
+            limit(false)
+            error(message)
+        end
+    end, '', interval)
+
+    local function limited(f)
+        limit(true)
+        f()
+        limit(false)
+    end
+
+    local internal = {
+        tis_has_tile = tis_has_tile,
+        tis_create_node = tis_create_node,
+        tis_call_node = tis_call_node,
+        tis_execute = function(code)
+            local f, message = load(code, '=spec', 't')
+            if f then
+                local ok
+                ok, message = pcall(limited, f)
+                limit(false)
+                if ok then
+                    return
+                end
+            end
+            error(message, 0)
+        end,
+    }
+
+    local allowed = {
+        math = true, string = true, table = true, register_tile = true,
+        assert = true, error = true, ipairs = true, next = true, pairs = true, select = true,
+        tonumber = true, tostring = true, type = true, unpack = true,
+    }
+
+    for name in pairs(G) do
+        if not allowed[name] then
+            G[name] = nil
+        end
+    end
+
+    G.string.dump = nil
+
+    setmetatable(G, {
+        __index = internal,
+        __newindex = function(t, name, value)
+            if internal[name] ~= nil then
+                error('cannot replace ' .. name, 2)
+            end
+            rawset(t, name, value)
+        end,
+    })
+";
+
+/// Constants for extracting the puzzle name from the spec.
+const NAME_VAR: &'static str = "name";
+const NAME_FN: &'static str = "get_name";
+const NAME_FN_EXEC: &'static str = "name = get_name()";
+
+/// Constants for extracting the puzzle description from the spec.
+const DESCRIPTION_TABLE: &'static str = "description";
+const DESCRIPTION_FN: &'static str = "get_description";
+const DESCRIPTION_FN_EXEC: &'static str = "description = get_description()";
+
+/// Constants for extracting the TIS-100 layout from the spec.
+const LAYOUT_TABLE: &'static str = "layout";
+const LAYOUT_FN: &'static str = "get_layout";
+const LAYOUT_FN_EXEC: &'static str = "layout = get_layout()";
+
+/// Constants for extracting the TIS-100 test streams from the spec.
+const STREAMS_TABLE: &'static str = "streams";
+const STREAMS_FN: &'static str = "get_streams";
+const STREAMS_FN_EXEC: &'static str = "streams = get_streams()";
+const STREAM_KIND_IDX: u32 = 1;
+const STREAM_NAME_IDX: u32 = 2;
+const STREAM_NODE_IDX: u32 = 3;
+const STREAM_DATA_IDX: u32 = 4;
+
+/// Enumerations for the stream kinds.
+const STREAM_INPUT: u32 = 0;
+const STREAM_OUTPUT: u32 = 1;
+const STREAM_IMAGE: u32 = 2;
+
+/// Enumerations for the tile kinds.
+const TILE_COMPUTE: u32 = 0;
+const TILE_MEMORY: u32 = 1;
+const TILE_DAMAGED: u32 = 2;
+const TILE_QUEUE: u32 = 3;
+const TILE_RAM: u32 = 4;
+
+/// Enumerations for the ports that custom tiles can read and write.
+const PORT_UP: u32 = 0;
+const PORT_DOWN: u32 = 1;
+const PORT_LEFT: u32 = 2;
+const PORT_RIGHT: u32 = 3;
+
+/// A pointer to the `IoBusView` of the custom tile that is currently running, if any. The Rust
+/// functions that are called by the tile's `io` wrapper access the view through this pointer, which
+/// is only set while a `ViewGuard` is alive.
+type ViewCell = Rc<Cell<*mut IoBusView<'static>>>;
+
+/// Stores a view in a `ViewCell` for as long as the guard is alive. The guard borrows the view, so
+/// the pointer can't outlive it, and the pointer is cleared when the guard is dropped, even if the
+/// call that it was created for panics.
+struct ViewGuard<'a> {
+    cell: &'a ViewCell,
+    marker: PhantomData<&'a mut ()>,
+}
+
+impl<'a> ViewGuard<'a> {
+    /// Store the view in the cell until the guard is dropped.
+    fn new(cell: &'a ViewCell, io: &'a mut IoBusView) -> ViewGuard<'a> {
+        assert!(cell.get().is_null(), "custom tile views can't be nested");
+        cell.set(io as *mut IoBusView as *mut IoBusView<'static>);
+
+        ViewGuard {
+            cell: cell,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a> Drop for ViewGuard<'a> {
+    fn drop(&mut self) {
+        self.cell.set(ptr::null_mut());
+    }
+}
+
+/// Tracks how much memory a Lua context has allocated. The limit is only enforced while the spec's
+/// code is running, so that allocations made by hlua can't fail outside of a protected call.
+struct Memory {
+    used: Cell<usize>,
+    limited: Cell<bool>,
+}
+
+/// Tracks how much of its execution budget a Lua context has used. The sandbox's count hook
+/// reports to the budget every `BUDGET_INTERVAL` instructions.
+struct Budget {
+    instructions: Cell<u32>,
+    max_instructions: Cell<u32>,
+    start: Cell<Instant>,
+    max_time: Cell<Duration>,
+}
+
+impl Budget {
+    /// Construct a new `Budget` with the given limits.
+    fn new(max_instructions: u32, max_time: Duration) -> Budget {
+        Budget {
+            instructions: Cell::new(0),
+            max_instructions: Cell::new(max_instructions),
+            start: Cell::new(Instant::now()),
+            max_time: Cell::new(max_time),
+        }
+    }
+
+    /// Start a new budget with the given limits.
+    fn reset(&self, max_instructions: u32, max_time: Duration) {
+        self.instructions.set(0);
+        self.max_instructions.set(max_instructions);
+        self.start.set(Instant::now());
+        self.max_time.set(max_time);
+    }
+
+    /// Record that some instructions have been executed, and check if there is any budget left.
+    fn spend(&self, instructions: u32) -> bool {
+        self.instructions.set(self.instructions.get().saturating_add(instructions));
+        self.instructions.get() <= self.max_instructions.get() &&
+            self.start.get().elapsed() <= self.max_time.get()
+    }
+}
+
+/// The Lua context of a spec with custom tiles. The context is shared by all of the nodes that are
+/// created for the spec's tiles.
+struct Script {
+    lua: RefCell<Lua<'static>>,
+    // Closing the Lua context frees its memory, so the `Memory` must be dropped after it.
+    #[allow(unused)]
+    memory: Rc<Memory>,
+    budget: Rc<Budget>,
+    view: ViewCell,
+    next_id: Cell<u32>,
+}
+
+impl Script {
+    /// Create a node for the custom tile with the given name.
+    fn create_node(script: &Rc<Script>, name: &str) -> LuaNode {
+        let id = script.next_id.get();
+        script.next_id.set(id + 1);
+
+        script.budget.reset(NODE_MAX_INSTRUCTIONS, Duration::from_millis(NODE_MAX_MILLIS));
+        let create = format!("tis_create_node({}, {})", id, quote(name));
+        let failed = !try_execute(&mut script.lua.borrow_mut(), &create).unwrap_or(false);
+
+        LuaNode {
+            script: script.clone(),
+            id: id,
+            failed: failed,
+        }
+    }
+}
+
+/// A node for a custom tile, whose behavior is defined by the spec's Lua code. If the Lua code
+/// raises an error or exceeds its budget, then the node stops running and behaves like a damaged
+/// node.
+struct LuaNode {
+    script: Rc<Script>,
+    id: u32,
+    failed: bool,
+}
+
+impl LuaNode {
+    /// Call one of the node's Lua functions with access to its `IoBusView`.
+    fn call(&mut self, method: &str, io: &mut IoBusView) {
+        if self.failed {
+            return;
+        }
+
+        let _guard = ViewGuard::new(&self.script.view, io);
+        self.script.budget.reset(NODE_MAX_INSTRUCTIONS, Duration::from_millis(NODE_MAX_MILLIS));
+
+        let call = format!("tis_call_node({}, '{}')", self.id, method);
+        if !try_execute(&mut self.script.lua.borrow_mut(), &call).unwrap_or(false) {
+            self.failed = true;
+        }
+    }
+}
+
+impl Node for LuaNode {
+    fn step(&mut self, io: &mut IoBusView) {
+        self.call("step", io);
+    }
+
+    fn sync(&mut self, io: &mut IoBusView) {
+        self.call("sync", io);
+    }
+}
+
+/// Load a `Spec` from a Lua file, using the given seed for the random number generator.
+pub fn from_file(filename: &str, save: Save, registry: Registry, seed: u32) -> Result<Spec, SpecError> {
+    let mut src = String::new();
+    match File::open(&Path::new(filename)) {
+        Ok(mut file) => if let Err(_) = file.read_to_string(&mut src) {
+            return Err(ReadFileFailed);
+        },
+        Err(_) => return Err(ReadFileFailed),
+    }
+
+    from_str(&src, save, registry, seed)
+}
+
+/// Load a `Spec` from the source of a Lua file, using the given seed for the random number
+/// generator. The source is kept so that the streams can be regenerated with a different seed.
+/// Custom tiles that are registered by the spec are added to the registry.
+pub fn from_str(src: &str, save: Save, mut registry: Registry, seed: u32) -> Result<Spec, SpecError> {
+    // Prepare the Lua context. The memory is declared first so that it outlives the context.
+    let memory = Rc::new(Memory {
+        used: Cell::new(0),
+        limited: Cell::new(false),
+    });
+    let mut lua = new_lua(&memory);
+    lua.openlibs();
+
+    let budget = Rc::new(Budget::new(MAX_INSTRUCTIONS, Duration::from_secs(MAX_SECONDS)));
+    let view: ViewCell = Rc::new(Cell::new(ptr::null_mut()));
+    set_budget_functions(&mut lua, &budget, &memory);
+
+    let seed_random = format!("(function(...) {} end)({})", SEED_RANDOM_EXEC, seed);
+    if let Err(_) = lua.execute::<()>(&seed_random) {
+        return Err(SeedRandomFailed);
+    }
+
+    set_view_functions(&mut lua, &view);
+    if let Err(_) = lua.execute::<()>(TILES_EXEC) {
+        return Err(SandboxFailed);
+    }
+
+    let sandbox = format!("(function(...) {} end)({}, '{}')", SANDBOX_EXEC, BUDGET_INTERVAL, BUDGET_ERROR);
+    if let Err(_) = lua.execute::<()>(&sandbox) {
+        return Err(SandboxFailed);
+    }
+
+    lua.set("STREAM_INPUT", STREAM_INPUT);
+    lua.set("STREAM_OUTPUT", STREAM_OUTPUT);
+    lua.set("STREAM_IMAGE", STREAM_IMAGE);
+    lua.set("TILE_COMPUTE", TILE_COMPUTE);
+    lua.set("TILE_MEMORY", TILE_MEMORY);
+    lua.set("TILE_DAMAGED", TILE_DAMAGED);
+    lua.set("TILE_QUEUE", TILE_QUEUE);
+    lua.set("TILE_RAM", TILE_RAM);
+    lua.set("PORT_UP", PORT_UP);
+    lua.set("PORT_DOWN", PORT_DOWN);
+    lua.set("PORT_LEFT", PORT_LEFT);
+    lua.set("PORT_RIGHT", PORT_RIGHT);
+
+    // Execute the spec file.
+    execute(&mut lua, src, ReadFileFailed)?;
+
+    // The name and description are optional, since they aren't needed to run the puzzle.
+    let mut name = String::new();
+    if lua.get::<LuaFunction<_>, _>(NAME_FN).is_some() {
+        if try_execute(&mut lua, NAME_FN_EXEC)? {
+            if let Some(value) = lua.get::<String, _>(NAME_VAR) {
+                name = value;
+            }
+        }
+    }
+
+    let mut description = Vec::new();
+    if lua.get::<LuaFunction<_>, _>(DESCRIPTION_FN).is_some() {
+        if try_execute(&mut lua, DESCRIPTION_FN_EXEC)? {
+            if let Some(mut description_table) = lua.get::<LuaTable<_>, _>(DESCRIPTION_TABLE) {
+                for (_, v) in description_table.iter::<u32, String>().filter_map(|e| e) {
+                    description.push(v);
+                }
+            }
+        }
+    }
+
+    // Make sure that get_layout exists and can be called.
+    if let None = lua.get::<LuaFunction<_>, _>(LAYOUT_FN) {
+        return Err(GetLayoutFailed);
+    }
+
+    // FIXME: Figure out how to return a LuaTable from a LuaFunction call.
+    //        For now we call the get_layout function and save the result table to a variable.
+    execute(&mut lua, LAYOUT_FN_EXEC, GetLayoutFailed)?;
+
+    // Read the layout from Lua. Custom tiles are identified by the name returned from
+    // register_tile, so each entry is either a number or a string.
+    let mut layout = Vec::new();
+    if let Some(mut layout_table) = lua.get::<LuaTable<_>, _>(LAYOUT_TABLE) {
+        for index in 1..(NUM_NODES as u32 + 1) {
+            if let Some(value) = layout_table.get::<u32, _>(index) {
+                match value {
+                    TILE_COMPUTE => layout.push(Compute),
+                    TILE_MEMORY => layout.push(Memory),
+                    TILE_DAMAGED => layout.push(Damaged),
+                    TILE_QUEUE => layout.push(Queue),
+                    TILE_RAM => layout.push(Ram),
+                    _ => return Err(GetLayoutFailed),
+                };
+            } else if let Some(name) = layout_table.get::<String, _>(index) {
+                layout.push(Tile::Custom(name));
+            } else {
+                break;
+            }
+        }
+
+        if layout.len() != NUM_NODES {
+            return Err(GetLayoutFailed);
+        }
+    }
+
+    // Make sure that get_streams exists and can be called.
+    if let None = lua.get::<LuaFunction<_>, _>(STREAMS_FN) {
+        return Err(GetStreamsFailed);
+    }
+
+    // FIXME: Figure out how to return a LuaTable from a LuaFunction call.
+    //        For now we call the get_streams function and save the result table to a variable.
+    execute(&mut lua, STREAMS_FN_EXEC, GetStreamsFailed)?;
+
+    // Read the streams from Lua.
+    let mut streams = Vec::new();
+    if let Some(mut streams_table) = lua.get::<LuaTable<_>, _>(STREAMS_TABLE) {
+        // FIXME: Figure out how to iterate over a table of tables.
+        //        For now, we can only have 8 total inputs and outputs, so just try each index.
+        for index in 1..9 {
+            // Each stream is a table with the following format:
+            // 1: kind (input, output, image)
+            // 2: name
+            // 3: node the stream is connected to
+            // 4: data stream
+            if let Some(mut stream_table) = streams_table.get::<LuaTable<_>, _>(index) {
+                // Custom streams are identified by the name they were registered with.
+                let kind = match stream_table.get::<u32, _>(STREAM_KIND_IDX) {
+                    Some(STREAM_INPUT) => Input,
+                    Some(STREAM_OUTPUT) => Output,
+                    Some(STREAM_IMAGE) => Image,
+                    Some(_) => return Err(GetStreamsFailed),
+                    None => match stream_table.get::<String, _>(STREAM_KIND_IDX) {
+                        Some(name) => StreamKind::Custom(name),
+                        None => return Err(GetStreamsFailed),
+                    },
+                };
+
+                let name = match stream_table.get::<String, _>(STREAM_NAME_IDX) {
+                    Some(name) => name,
+                    None => return Err(GetStreamsFailed),
+                };
+
+                let node = match stream_table.get::<u32, _>(STREAM_NODE_IDX) {
+                    Some(node) => node as usize,
+                    None => return Err(GetStreamsFailed),
+                };
+
+                let data = match stream_table.get::<LuaTable<_>, _>(STREAM_DATA_IDX) {
+                    Some(mut data_table) => {
+                        let mut data = Vec::new();
+                        for (_, v) in data_table.iter::<u32, i32>().filter_map(|e| e) {
+                            data.push(v as isize);
+                        }
+                        data
+                    },
+                    None => return Err(GetStreamsFailed),
+                };
+
+                streams.push(Stream {
+                    kind: kind,
+                    name: name,
+                    node: node,
+                    data: data,
+                });
+            } else {
+                break;
+            }
+        }
+    }
+
+    // Find the custom tiles in the layout that were registered by the spec. Any other custom tiles
+    // must be provided by the registry.
+    let mut names = Vec::new();
+    for tile in layout.iter() {
+        if let Tile::Custom(ref name) = *tile {
+            if !names.contains(name) {
+                if let Ok(true) = lua.execute::<bool>(&format!("return tis_has_tile({})", quote(name))) {
+                    names.push(name.clone());
+                }
+            }
+        }
+    }
+
+    // The Lua context is only kept if it is needed to run the custom tiles.
+    if !names.is_empty() {
+        let script = Rc::new(Script {
+            lua: RefCell::new(lua),
+            memory: memory,
+            budget: budget,
+            view: view,
+            next_id: Cell::new(0),
+        });
+
+        for name in names.iter() {
+            let script = script.clone();
+            let tile = name.clone();
+            registry.register_tile(name, move || Box::new(Script::create_node(&script, &tile)));
+        }
+    }
+
+    let source = src.to_string();
+
+    Ok(Spec {
+        save: save,
+        name: name,
+        description: description,
+        layout: layout,
+        streams: streams,
+        seed: Some(seed),
+        generator: Some(Box::new(move |seed| {
+            from_str(&source, Save::new(), Registry::new(), seed).map(|spec| spec.streams)
+        })),
+        registry: registry,
+    })
+}
+
+/// Create a Lua context whose allocations are tracked by the given `Memory`.
+fn new_lua(memory: &Rc<Memory>) -> Lua<'static> {
+    let ud = &**memory as *const Memory as *mut c_void;
+    let state = unsafe { lua52_sys::lua_newstate(allocate, ud) };
+    if state.is_null() {
+        panic!("lua_newstate failed");
+    }
+
+    unsafe { Lua::from_existing_state(state, true) }
+}
+
+/// The allocator for a spec's Lua context. Growing an allocation fails while the `Memory` is
+/// limited and the context would use more than `MAX_MEMORY` bytes, which Lua reports as an error.
+extern "C" fn allocate(ud: *mut c_void, ptr: *mut c_void, osize: size_t, nsize: size_t) -> *mut c_void {
+    // The context never outlives its memory.
+    let memory = unsafe { &*(ud as *const Memory) };
+
+    // When a new block is allocated, osize is the type of the object rather than a size.
+    let osize = if ptr.is_null() { 0 } else { osize };
+    let used = memory.used.get() - osize;
+
+    if nsize == 0 {
+        unsafe { libc::free(ptr) };
+        memory.used.set(used);
+        return ptr::null_mut();
+    }
+
+    if nsize > osize && memory.limited.get() && used + nsize > MAX_MEMORY {
+        return ptr::null_mut();
+    }
+
+    let block = unsafe { libc::realloc(ptr, nsize) };
+    if !block.is_null() {
+        memory.used.set(used + nsize);
+    }
+
+    block
+}
+
+/// Set the Rust functions that are used by the sandbox to enforce the budget and the memory limit.
+/// The sandbox removes them from the globals before the spec is executed.
+fn set_budget_functions(lua: &mut Lua, budget: &Rc<Budget>, memory: &Rc<Memory>) {
+    let budget = budget.clone();
+    lua.set("tis_spend_budget", hlua::function1(move |instructions: u32| budget.spend(instructions)));
+
+    let memory = memory.clone();
+    lua.set("tis_limit_memory", hlua::function1(move |limited: bool| memory.limited.set(limited)));
+}
+
+/// Set the Rust functions that are used by the `io` wrapper for custom tiles. `TILES_EXEC` removes
+/// them from the globals before the spec is executed.
+fn set_view_functions(lua: &mut Lua, view: &ViewCell) {
+    let read_view = view.clone();
+    lua.set("tis_read", hlua::function1(move |port: u32| {
+        match with_view(&read_view, |io| port_from_constant(port).and_then(|port| io.read(port))) {
+            Some(Some(value)) => (true, value as i32),
+            _ => (false, 0),
+        }
+    }));
+
+    let write_view = view.clone();
+    lua.set("tis_write", hlua::function2(move |port: u32, value: i32| {
+        if let Some(port) = port_from_constant(port) {
+            let value = value.max(-999).min(999) as isize;
+            with_view(&write_view, |io| io.write(port, value));
+        }
+    }));
+
+    let blocked_view = view.clone();
+    lua.set("tis_is_blocked", hlua::function0(move || {
+        with_view(&blocked_view, |io| io.is_blocked()).unwrap_or(false)
+    }));
+}
+
+/// Call a function with the `IoBusView` of the custom tile that is currently running. If no tile is
+/// running, for example because the spec called the function while it was being loaded, then the
+/// function isn't called.
+fn with_view<T, F>(view: &ViewCell, f: F) -> Option<T>
+    where F: FnOnce(&mut IoBusView) -> T
+{
+    let io = view.get();
+    if io.is_null() {
+        None
+    } else {
+        // The pointer is only set while a `ViewGuard` holds the borrow of the view.
+        Some(f(unsafe { &mut *io }))
+    }
+}
+
+/// Get the port for one of the `PORT_*` constants.
+fn port_from_constant(port: u32) -> Option<Port> {
+    match port {
+        PORT_UP => Some(UP),
+        PORT_DOWN => Some(DOWN),
+        PORT_LEFT => Some(LEFT),
+        PORT_RIGHT => Some(RIGHT),
+        _ => None,
+    }
+}
+
+/// Execute a chunk of Lua code in the spec's context. If the code fails, then the given error is
+/// returned, unless the failure was caused by the spec exceeding its execution budget.
+fn execute(lua: &mut Lua, code: &str, error: SpecError) -> Result<(), SpecError> {
+    if try_execute(lua, code)? {
+        Ok(())
+    } else {
+        Err(error)
+    }
+}
+
+/// Execute a chunk of Lua code in the spec's context, and return whether it succeeded. Exceeding
+/// the execution budget or the memory limit is still treated as an error.
+fn try_execute(lua: &mut Lua, code: &str) -> Result<bool, SpecError> {
+    match lua.execute::<()>(&format!("tis_execute({})", quote(code))) {
+        Ok(_) => Ok(true),
+        Err(LuaError::ExecutionError(ref msg))
+            if msg.contains(BUDGET_ERROR) || msg.contains(MEMORY_ERROR) => Err(BudgetExceeded),
+        Err(_) => Ok(false),
+    }
+}
+
+/// Convert a `Spec` to the source of a Lua file that can be loaded by the game.
+pub fn to_lua(spec: &Spec) -> String {
+    let mut src = String::new();
+
+    src.push_str("function get_name()\n");
+    src.push_str(&format!("    return {}\n", quote(&spec.name)));
+    src.push_str("end\n\n");
+
+    src.push_str("function get_description()\n");
+    src.push_str("    return {\n");
+    for line in spec.description.iter() {
+        src.push_str(&format!("        {},\n", quote(line)));
+    }
+    src.push_str("    }\n");
+    src.push_str("end\n\n");
+
+    src.push_str("function get_streams()\n");
+    src.push_str("    return {\n");
+    for stream in spec.streams.iter() {
+        let data = stream.data.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+        src.push_str(&format!("        {{ {}, {}, {}, {{ {} }} }},\n",
+                              stream_constant(&stream.kind),
+                              quote(&stream.name),
+                              stream.node,
+                              data.join(", ")));
+    }
+    src.push_str("    }\n");
+    src.push_str("end\n\n");
+
+    src.push_str("function get_layout()\n");
+    src.push_str("    return {\n");
+    for row in spec.layout.chunks(4) {
+        let tiles = row.iter().map(tile_constant).collect::<Vec<_>>();
+        src.push_str(&format!("        {},\n", tiles.join(", ")));
+    }
+    src.push_str("    }\n");
+    src.push_str("end\n");
+
+    src
+}
+
+/// Get the Lua constant for a tile kind. Custom tiles are written as their names. The game won't be
+/// able to load queue, RAM or custom tiles.
+fn tile_constant(tile: &Tile) -> String {
+    match *tile {
+        Compute => "TILE_COMPUTE".to_string(),
+        Memory => "TILE_MEMORY".to_string(),
+        Damaged => "TILE_DAMAGED".to_string(),
+        Queue => "TILE_QUEUE".to_string(),
+        Ram => "TILE_RAM".to_string(),
+        Tile::Custom(ref name) => quote(name),
+    }
+}
+
+/// Get the Lua constant for a stream kind. Custom streams are written as their names, and the game
+/// won't be able to load them.
+fn stream_constant(kind: &StreamKind) -> String {
+    match *kind {
+        Input => "STREAM_INPUT".to_string(),
+        Output => "STREAM_OUTPUT".to_string(),
+        Image => "STREAM_IMAGE".to_string(),
+        StreamKind::Custom(ref name) => quote(name),
+    }
+}
+
+/// Quote a string as a Lua string literal.
+fn quote(s: &str) -> String {
+    let mut quoted = String::from("\"");
+
+    for c in s.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\{:03}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+#[test]
+fn test_quote() {
+    assert_eq!(quote("IN.A"), "\"IN.A\"");
+    assert_eq!(quote("A \"B\" \\ C\n"), "\"A \\\"B\\\" \\\\ C\\n\"");
+}
+
+#[test]
+fn test_lua_round_trip() {
+    let src = "function get_name() return \"TEST \\\"1\\\"\" end\n\
+               function get_description() return { \"LINE 1\", \"LINE 2\" } end\n\
+               function get_streams()\n\
+                   return {\n\
+                       { STREAM_INPUT, \"IN.A\", 0, { 1, -2, 3 } },\n\
+                       { STREAM_OUTPUT, \"OUT.A\", 3, { 4, 5 } },\n\
+                   }\n\
+               end\n\
+               function get_layout()\n\
+                   return {\n\
+                       TILE_COMPUTE, TILE_MEMORY, TILE_COMPUTE, TILE_DAMAGED,\n\
+                       TILE_QUEUE, TILE_COMPUTE, TILE_COMPUTE, TILE_RAM,\n\
+                       TILE_COMPUTE, TILE_COMPUTE, TILE_MEMORY, TILE_COMPUTE,\n\
+                   }\n\
+               end\n";
+
+    let spec = from_str(src, Save::new(), Registry::new(), 0).ok().unwrap();
+    let copy = from_str(&to_lua(&spec), Save::new(), Registry::new(), 0).ok().unwrap();
+
+    assert_eq!(spec.name, "TEST \"1\"");
+    assert_eq!(copy.name, spec.name);
+    assert_eq!(copy.description, spec.description);
+    assert_eq!(copy.layout, spec.layout);
+    assert_eq!(copy.streams, spec.streams);
+}
+
+#[test]
+fn test_sandbox() {
+    let layout = "function get_layout()\n\
+                      return {\n\
+                          TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,\n\
+                          TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,\n\
+                          TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,\n\
+                      }\n\
+                  end\n";
+
+    // The safe libraries are available.
+    let src = format!("{}function get_streams()\n\
+                           local n = math.floor(string.len(table.concat({{ 'a', 'b' }})))\n\
+                           return {{ {{ STREAM_INPUT, 'IN', 0, {{ n }} }} }}\n\
+                       end\n", layout);
+    let spec = from_str(&src, Save::new(), Registry::new(), 0).ok().unwrap();
+    assert_eq!(spec.streams[0].data, vec![2]);
+
+    // The unsafe libraries are not.
+    for global in ["io", "os", "debug", "require", "dofile", "load", "pcall", "rawset", "setmetatable",
+                   "tis_read", "tis_spend_budget", "tis_limit_memory"].iter() {
+        let src = format!("{}function get_streams() return {{ {{ STREAM_INPUT, 'IN', 0, {{ {} == nil and 1 or 0 }} }} }} end\n",
+                          layout, global);
+        let spec = from_str(&src, Save::new(), Registry::new(), 0).ok().unwrap();
+        assert_eq!(spec.streams[0].data, vec![1]);
+    }
+
+    // Infinite loops fail once the budget is exceeded.
+    let src = format!("{}function get_streams() while true do end end\n", layout);
+    match from_str(&src, Save::new(), Registry::new(), 0) {
+        Err(BudgetExceeded) => (),
+        _ => panic!("expected the spec to exceed its budget"),
+    }
+
+    // So do single calls that allocate too much memory.
+    let bodies = ["string.rep('x', 1e9)",
+                  "local s = 'x' for i = 1, 40 do s = s .. s end",
+                  "local t, s = {}, string.rep('x', 1e6) for i = 1, 1000 do t[i] = s end table.concat(t)"];
+    for body in bodies.iter() {
+        let src = format!("{}function get_streams() {} end\n", layout, body);
+        match from_str(&src, Save::new(), Registry::new(), 0) {
+            Err(BudgetExceeded) => (),
+            _ => panic!("expected the spec to exceed its memory limit"),
+        }
+    }
+
+    // The functions that run custom tiles can't be replaced.
+    let src = format!("{}tis_call_node = function() end\n\
+                       function get_streams() return {{}} end\n", layout);
+    match from_str(&src, Save::new(), Registry::new(), 0) {
+        Err(ReadFileFailed) => (),
+        _ => panic!("expected the spec to fail"),
+    }
+}
+
+#[test]
+fn test_custom_tile() {
+    use save::parse_save;
+    use node::TestState::*;
+    use machine::Puzzle;
+
+    let src = "TILE_DOUBLE = register_tile(\"DOUBLE\", function()\n\
+                   local value = nil\n\
+                   return {\n\
+                       step = function(io)\n\
+                           if value == nil then\n\
+                               value = io:read(PORT_UP)\n\
+                               if value ~= nil then io:write(PORT_DOWN, value * 2) end\n\
+                           end\n\
+                       end,\n\
+                       sync = function(io)\n\
+                           if value ~= nil and not io:is_blocked() then value = nil end\n\
+                       end,\n\
+                   }\n\
+               end)\n\
+               function get_streams()\n\
+                   return {\n\
+                       { STREAM_INPUT, \"IN.A\", 1, { 1, 2, 600 } },\n\
+                       { STREAM_OUTPUT, \"OUT.A\", 1, { 2, 4, 999 } },\n\
+                   }\n\
+               end\n\
+               function get_layout()\n\
+                   return {\n\
+                       TILE_COMPUTE, TILE_DOUBLE, TILE_COMPUTE, TILE_COMPUTE,\n\
+                       TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,\n\
+                       TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,\n\
+                   }\n\
+               end\n";
+
+    let save = parse_save("@5\nMOV UP DOWN\n@9\nMOV UP DOWN\n").unwrap();
+    let mut spec = from_str(src, save, Registry::new(), 0).ok().unwrap();
+    assert_eq!(spec.layout[1], Tile::Custom("DOUBLE".to_string()));
+
+    let mut puzzle = Puzzle::from_spec(&mut spec).ok().unwrap();
+    while puzzle.state() == Testing && puzzle.cycles() < 1000 {
+        puzzle.step();
+    }
+
+    assert_eq!(puzzle.state(), Passed);
+
+    // Tiles that aren't registered by the spec must be provided by the registry.
+    let src = src.replace("register_tile(", "(function(name, factory) return name end)(");
+    let mut spec = from_str(&src, Save::new(), Registry::new(), 0).ok().unwrap();
+    assert!(spec.check().is_err());
+
+    // Setting up the puzzle fails too, rather than running with a damaged node in its place.
+    assert!(Puzzle::from_spec(&mut spec).is_err());
+}
+
+#[test]
+fn test_view_guard() {
+    use io::IoBus;
+
+    let mut bus = IoBus::new();
+    bus.connect_full(0, 1, DOWN);
+    let mut io = bus.view(0);
+    let view: ViewCell = Rc::new(Cell::new(ptr::null_mut()));
+
+    {
+        let _guard = ViewGuard::new(&view, &mut io);
+        assert_eq!(with_view(&view, |_| ()), Some(()));
+    }
+
+    // Once the call is over, the view can't be reached.
+    assert_eq!(with_view(&view, |_| ()), None);
+}
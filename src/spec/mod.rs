@@ -0,0 +1,693 @@
+//! Constructs for specifying TIS-100 puzzles.
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use vec_map::VecMap;
+use save::Save;
+use node::{Node, TestNode, BasicExecutionNode, DamagedExecutionNode, TestInputNode, TestOutputNode, TestImageNode};
+use node::{StackMemoryNode, QueueMemoryNode, RandomAccessMemoryNode};
+use machine::{NUM_NODES, INPUT_0, Tis100};
+
+pub use self::registry::{Registry, TileFactory, InputFactory, TestFactory};
+
+pub mod library;
+
+mod lua;
+mod decl;
+mod registry;
+
+/// The different kinds of nodes available to the spec. `Custom` tiles are defined by the spec or
+/// by a `Registry`, and are identified by the name that they were registered with.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum Tile {
+    Compute,
+    Memory,
+    Queue,
+    Ram,
+    Damaged,
+    Custom(String),
+}
+
+use self::Tile::*;
+
+impl From<String> for Tile {
+    fn from(name: String) -> Tile {
+        match name.as_str() {
+            "compute" => Compute,
+            "memory" => Memory,
+            "queue" => Queue,
+            "ram" => Ram,
+            "damaged" => Damaged,
+            _ => Tile::Custom(name),
+        }
+    }
+}
+
+impl From<Tile> for String {
+    fn from(tile: Tile) -> String {
+        match tile {
+            Compute => "compute".to_string(),
+            Memory => "memory".to_string(),
+            Queue => "queue".to_string(),
+            Ram => "ram".to_string(),
+            Damaged => "damaged".to_string(),
+            Tile::Custom(name) => name,
+        }
+    }
+}
+
+/// A test stream, which is connected to the input or output of a node in the top or bottom row.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Stream {
+    pub kind: StreamKind,
+    pub name: String,
+    pub node: usize,
+    pub data: Vec<isize>
+}
+
+impl Stream {
+    /// Construct a new `Stream`.
+    pub fn new(kind: StreamKind, name: &str, node: usize, data: Vec<isize>) -> Stream {
+        Stream {
+            kind: kind,
+            name: name.to_string(),
+            node: node,
+            data: data,
+        }
+    }
+}
+
+/// The different kinds of streams available to the spec. `Custom` streams are defined by a
+/// `Registry`, and are identified by the name that they were registered with.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum StreamKind {
+    Input,
+    Output,
+    Image,
+    Custom(String),
+}
+
+use self::StreamKind::*;
+
+impl From<String> for StreamKind {
+    fn from(name: String) -> StreamKind {
+        match name.as_str() {
+            "input" => Input,
+            "output" => Output,
+            "image" => Image,
+            _ => StreamKind::Custom(name),
+        }
+    }
+}
+
+impl From<StreamKind> for String {
+    fn from(kind: StreamKind) -> String {
+        match kind {
+            Input => "input".to_string(),
+            Output => "output".to_string(),
+            Image => "image".to_string(),
+            StreamKind::Custom(name) => name,
+        }
+    }
+}
+
+/// An error that can be returned while loading a spec.
+#[derive(Debug)]
+pub enum SpecError {
+    SeedRandomFailed,
+    ReadFileFailed,
+    GetLayoutFailed,
+    GetStreamsFailed,
+    DecodeFailed,
+    EncodeFailed,
+    WriteFileFailed,
+    UnknownSegment,
+    SandboxFailed,
+    BudgetExceeded,
+}
+
+use self::SpecError::*;
+
+/// A function that generates the test streams for a puzzle from a seed.
+pub type Generator = Box<Fn(u32) -> Result<Vec<Stream>, SpecError>>;
+
+/// The definition of a TIS-100 puzzle: its layout, its test streams, and metadata describing it.
+/// A `Puzzle` can be built from any implementation of `PuzzleSpec`.
+///
+/// # Example
+///
+/// ```
+/// use tis_100::spec::{PuzzleSpec, Tile, Stream, SpecError};
+/// use tis_100::spec::StreamKind::*;
+///
+/// struct Negate;
+///
+/// impl PuzzleSpec for Negate {
+///     fn layout(&self) -> Vec<Tile> {
+///         vec![Tile::Compute; 12]
+///     }
+///
+///     fn streams(&self, seed: u32) -> Result<Vec<Stream>, SpecError> {
+///         let input = (0..39).map(|i| ((seed as isize + i * 7) % 100) - 50).collect::<Vec<_>>();
+///         let output = input.iter().map(|&v| -v).collect::<Vec<_>>();
+///
+///         Ok(vec![Stream::new(Input, "IN", 1, input), Stream::new(Output, "OUT", 2, output)])
+///     }
+/// }
+///
+/// let streams = Negate.streams(42).ok().unwrap();
+/// assert_eq!(streams[1].data[0], 8);
+/// ```
+pub trait PuzzleSpec {
+    /// Get the name of the puzzle.
+    fn name(&self) -> String {
+        String::new()
+    }
+
+    /// Get the description of the puzzle, one line per entry.
+    fn description(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Get the kind of node at each position of the TIS-100, in row-major order.
+    fn layout(&self) -> Vec<Tile>;
+
+    /// Generate the test streams for the puzzle. The same seed must always produce the same
+    /// streams.
+    fn streams(&self, seed: u32) -> Result<Vec<Stream>, SpecError>;
+
+    /// Get the registry that provides the spec's custom tiles and streams, if it has any.
+    fn registry(&self) -> Option<&Registry> {
+        None
+    }
+}
+
+/// The file formats that a spec can be stored in.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+enum Format {
+    Lua,
+    Toml,
+    Json,
+}
+
+impl Format {
+    /// Determine the format of a spec file from its extension. Files without a recognized
+    /// extension are assumed to be Lua.
+    fn from_filename(filename: &str) -> Format {
+        match Path::new(filename).extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Format::Toml,
+            Some("json") => Format::Json,
+            _ => Format::Lua,
+        }
+    }
+}
+
+/// A specification for a TIS-100 puzzle. Specifications configure the layout, inputs, and
+/// outputs for the TIS-100, along with the puzzle's name and description.
+///
+/// Specifications can either be Lua files or declarative TOML or JSON files. A Lua specification
+/// must provide at least the `get_layout` and `get_streams` functions, and may also provide
+/// `get_name` and `get_description`. Lua specifications are run in a restricted environment that
+/// only provides the `math`, `string` and `table` libraries, and they fail with `BudgetExceeded`
+/// if they run for too long or use too much memory. A declarative specification contains the same
+/// data directly:
+///
+/// ```toml
+/// name = "SIGNAL AMPLIFIER"
+/// description = ["READ A VALUE FROM IN.A", "DOUBLE THE VALUE", "WRITE THE VALUE TO OUT.A"]
+/// layout = [
+///     "compute", "compute", "compute", "damaged",
+///     "compute", "compute", "compute", "compute",
+///     "compute", "compute", "compute", "compute",
+/// ]
+///
+/// [[streams]]
+/// kind = "input"
+/// name = "IN.A"
+/// node = 1
+/// data = [12, 34, 56]
+///
+/// [[streams]]
+/// kind = "output"
+/// name = "OUT.A"
+/// node = 2
+/// data = [24, 68, 112]
+/// ```
+///
+/// Lua specifications can also define their own tiles with `register_tile`, which takes the name of
+/// the tile and a function that is called to create each node of that kind. The function returns a
+/// table whose optional `step` and `sync` functions are called with a wrapper around the node's
+/// `IoBusView` during each cycle. The wrapper provides `io:read(port)`, `io:write(port, value)` and
+/// `io:is_blocked()`, where `port` is one of `PORT_UP`, `PORT_DOWN`, `PORT_LEFT` or `PORT_RIGHT`.
+/// `register_tile` returns a constant that can be used in the layout:
+///
+/// ```lua
+/// TILE_RANDOM = register_tile("RANDOM", function()
+///     return {
+///         step = function(io)
+///             if not io:is_blocked() then
+///                 io:write(PORT_DOWN, math.random(0, 999))
+///             end
+///         end,
+///     }
+/// end)
+/// ```
+///
+/// Specs that generate their streams, such as Lua specs, are evaluated with a random seed when they
+/// are loaded. The streams can be regenerated with a different seed using `set_seed`.
+pub struct Spec {
+    save: Save,
+    name: String,
+    description: Vec<String>,
+    layout: Vec<Tile>,
+    streams: Vec<Stream>,
+    seed: Option<u32>,
+    generator: Option<Generator>,
+    registry: Registry,
+}
+
+impl Spec {
+    /// Load a `Spec` from a file. The format of the file is determined by its extension: `.toml`
+    /// and `.json` files are loaded as declarative specs, and all other files are loaded as Lua.
+    pub fn from_file(filename: &str, save: Save) -> Result<Spec, SpecError> {
+        Spec::from_file_with_registry(filename, save, Registry::new())
+    }
+
+    /// Load a `Spec` from a file, using the given registry for any custom tiles and streams that
+    /// the spec refers to.
+    pub fn from_file_with_registry(filename: &str, save: Save, registry: Registry) -> Result<Spec, SpecError> {
+        let spec = match Format::from_filename(filename) {
+            Format::Lua => lua::from_file(filename, save, registry, random_seed())?,
+            Format::Toml => decl::from_toml(&read_file(filename)?, save, registry)?,
+            Format::Json => decl::from_json(&read_file(filename)?, save, registry)?,
+        };
+
+        spec.check()?;
+        Ok(spec)
+    }
+
+    /// Load the `Spec` for one of the game's campaign puzzles by its segment ID.
+    pub fn from_segment(id: &str, save: Save) -> Result<Spec, SpecError> {
+        let spec = match library::find(id) {
+            Some(segment) => lua::from_str(segment.source, save, Registry::new(), random_seed())?,
+            None => return Err(UnknownSegment),
+        };
+
+        spec.check()?;
+        Ok(spec)
+    }
+
+    /// Make sure that every custom tile and stream used by the spec is in its registry.
+    fn check(&self) -> Result<(), SpecError> {
+        check(&self.layout, &self.streams, &self.registry)
+    }
+
+    /// Write the `Spec` to a file in the format given by the file's extension. Lua specs are
+    /// written using the data that was produced when the spec was evaluated.
+    pub fn write_file(&self, filename: &str) -> Result<(), SpecError> {
+        let src = match Format::from_filename(filename) {
+            Format::Lua => self.to_lua(),
+            Format::Toml => self.to_toml()?,
+            Format::Json => self.to_json()?,
+        };
+
+        match File::create(&Path::new(filename)) {
+            Ok(mut file) => file.write_all(src.as_bytes()).map_err(|_| WriteFileFailed),
+            Err(_) => Err(WriteFileFailed),
+        }
+    }
+
+    /// Convert the `Spec` to a Lua file using the game's `get_name`, `get_description`,
+    /// `get_streams` and `get_layout` functions.
+    pub fn to_lua(&self) -> String {
+        lua::to_lua(self)
+    }
+
+    /// Convert the `Spec` to the declarative TOML format.
+    pub fn to_toml(&self) -> Result<String, SpecError> {
+        decl::to_toml(self)
+    }
+
+    /// Convert the `Spec` to the declarative JSON format.
+    pub fn to_json(&self) -> Result<String, SpecError> {
+        decl::to_json(self)
+    }
+
+    /// Get the seed that was used to generate the test streams. Specs with fixed streams, such as
+    /// declarative specs, do not have a seed.
+    pub fn seed(&self) -> Option<u32> {
+        self.seed
+    }
+
+    /// Regenerate the test streams using the given seed. This has no effect for specs with fixed
+    /// streams.
+    pub fn set_seed(&mut self, seed: u32) -> Result<(), SpecError> {
+        if let Some(ref generator) = self.generator {
+            self.streams = generator(seed)?;
+            self.seed = Some(seed);
+        }
+
+        Ok(())
+    }
+
+    /// Configure a `Tis100` instance using the spec. This fails if a custom tile or stream isn't in
+    /// the spec's registry.
+    pub fn setup(&mut self, cpu: &mut Tis100) -> Result<(), SpecError> {
+        setup(&self.layout, &self.streams, &self.save, &self.registry, cpu)
+    }
+
+    /// Get the test streams that were generated when the spec was loaded, or by the last call to
+    /// `set_seed`.
+    pub fn test_streams(&self) -> &[Stream] {
+        &self.streams
+    }
+
+    /// Get the test output nodes used by the spec.
+    pub fn tests(&self) -> VecMap<Box<TestNode>> {
+        tests(&self.streams, &self.registry)
+    }
+}
+
+impl PuzzleSpec for Spec {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn description(&self) -> Vec<String> {
+        self.description.clone()
+    }
+
+    fn layout(&self) -> Vec<Tile> {
+        self.layout.clone()
+    }
+
+    /// Generate the test streams for the given seed. Specs with fixed streams always return the
+    /// same streams.
+    fn streams(&self, seed: u32) -> Result<Vec<Stream>, SpecError> {
+        match self.generator {
+            Some(ref generator) => generator(seed),
+            None => Ok(self.streams.clone()),
+        }
+    }
+
+    fn registry(&self) -> Option<&Registry> {
+        Some(&self.registry)
+    }
+}
+
+/// Builds a `Spec` from Rust code. Every tile is a compute node unless it is changed.
+///
+/// # Example
+///
+/// ```
+/// use tis_100::save::parse_save;
+/// use tis_100::spec::{SpecBuilder, PuzzleSpec, Tile};
+///
+/// let save = parse_save("@1\nMOV UP ACC\nADD ACC\nMOV ACC DOWN\n").unwrap();
+/// let spec = SpecBuilder::new()
+///     .name("SIGNAL AMPLIFIER")
+///     .description("READ A VALUE FROM IN.A AND DOUBLE IT")
+///     .tile(3, Tile::Damaged)
+///     .input("IN.A", 1, vec![1, 2, 3])
+///     .output("OUT.A", 2, vec![2, 4, 6])
+///     .build(save).ok().unwrap();
+///
+/// assert_eq!(spec.name(), "SIGNAL AMPLIFIER");
+/// assert_eq!(spec.layout()[3], Tile::Damaged);
+/// ```
+pub struct SpecBuilder {
+    name: String,
+    description: Vec<String>,
+    layout: Vec<Tile>,
+    streams: Vec<Stream>,
+    generator: Option<Generator>,
+    registry: Registry,
+}
+
+impl SpecBuilder {
+    /// Construct a new `SpecBuilder` for a puzzle with no streams.
+    pub fn new() -> SpecBuilder {
+        SpecBuilder {
+            name: String::new(),
+            description: Vec::new(),
+            layout: vec![Compute; NUM_NODES],
+            streams: Vec::new(),
+            generator: None,
+            registry: Registry::new(),
+        }
+    }
+
+    /// Set the name of the puzzle.
+    pub fn name(mut self, name: &str) -> SpecBuilder {
+        self.name = name.to_string();
+        self
+    }
+
+    /// Add a line to the description of the puzzle.
+    pub fn description(mut self, line: &str) -> SpecBuilder {
+        self.description.push(line.to_string());
+        self
+    }
+
+    /// Set the kind of node at the given position.
+    pub fn tile(mut self, index: usize, tile: Tile) -> SpecBuilder {
+        assert!(index < NUM_NODES);
+        self.layout[index] = tile;
+        self
+    }
+
+    /// Add a fixed test stream.
+    pub fn stream(mut self, stream: Stream) -> SpecBuilder {
+        self.streams.push(stream);
+        self
+    }
+
+    /// Add a fixed input stream to the given node in the top row.
+    pub fn input(self, name: &str, node: usize, data: Vec<isize>) -> SpecBuilder {
+        self.stream(Stream::new(Input, name, node, data))
+    }
+
+    /// Add a fixed output stream to the given node in the bottom row.
+    pub fn output(self, name: &str, node: usize, data: Vec<isize>) -> SpecBuilder {
+        self.stream(Stream::new(Output, name, node, data))
+    }
+
+    /// Add a fixed image stream to the given node in the bottom row.
+    pub fn image(self, name: &str, node: usize, data: Vec<isize>) -> SpecBuilder {
+        self.stream(Stream::new(Image, name, node, data))
+    }
+
+    /// Generate the test streams from a seed instead of using fixed streams. The generated
+    /// streams are used in place of any fixed streams.
+    pub fn generator<F>(mut self, generator: F) -> SpecBuilder
+        where F: Fn(u32) -> Result<Vec<Stream>, SpecError> + 'static
+    {
+        self.generator = Some(Box::new(generator));
+        self
+    }
+
+    /// Use the given registry for the custom tiles and streams in the puzzle.
+    pub fn registry(mut self, registry: Registry) -> SpecBuilder {
+        self.registry = registry;
+        self
+    }
+
+    /// Build the `Spec` with the given save. If the spec has a generator, then its streams are
+    /// generated from a random seed, and any error from the generator is returned.
+    pub fn build(self, save: Save) -> Result<Spec, SpecError> {
+        let mut spec = Spec {
+            save: save,
+            name: self.name,
+            description: self.description,
+            layout: self.layout,
+            streams: self.streams,
+            seed: None,
+            generator: self.generator,
+            registry: self.registry,
+        };
+
+        spec.set_seed(random_seed())?;
+        Ok(spec)
+    }
+}
+
+/// Make sure that every custom tile and stream in the given layout and streams is in the registry.
+fn check(layout: &[Tile], streams: &[Stream], registry: &Registry) -> Result<(), SpecError> {
+    for tile in layout.iter() {
+        if let Tile::Custom(ref name) = *tile {
+            if !registry.has_tile(name) {
+                return Err(GetLayoutFailed);
+            }
+        }
+    }
+
+    for stream in streams.iter() {
+        if let StreamKind::Custom(ref name) = stream.kind {
+            if !registry.has_stream(name) {
+                return Err(GetStreamsFailed);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Configure a `Tis100` instance with the given layout and streams. Compute nodes are loaded with
+/// their programs from the save, and custom tiles and input streams are created by the registry.
+/// If a custom tile or stream isn't in the registry, then an error is returned before any nodes are
+/// added.
+pub fn setup(layout: &[Tile], streams: &[Stream], save: &Save, registry: &Registry, cpu: &mut Tis100) -> Result<(), SpecError> {
+    check(layout, streams, registry)?;
+
+    for (index, tile) in layout.iter().enumerate() {
+        let node: Box<Node> = match *tile {
+            Compute => match save.get(index) {
+                Some(prog) => Box::new(BasicExecutionNode::with_program(prog.clone())),
+                None => Box::new(BasicExecutionNode::new()),
+            },
+            Memory => Box::new(StackMemoryNode::new()),
+            Queue => Box::new(QueueMemoryNode::new()),
+            Ram => Box::new(RandomAccessMemoryNode::new()),
+            Damaged => Box::new(DamagedExecutionNode),
+            Tile::Custom(ref name) => match registry.create_node(name) {
+                Some(node) => node,
+                None => return Err(GetLayoutFailed),
+            },
+        };
+
+        cpu.add_node(index, node);
+    }
+
+    // Test inputs are added as regular nodes since we probably don't need to interact with
+    // them after they are set up.
+    for stream in streams.iter() {
+        let node: Box<Node> = match stream.kind {
+            Input => Box::new(TestInputNode::with_data(&stream.data)),
+            StreamKind::Custom(ref name) => match registry.create_input(name, stream) {
+                Some(node) => node,
+                None => continue,
+            },
+            _ => continue,
+        };
+
+        cpu.add_node(stream.node + INPUT_0, node);
+    }
+
+    Ok(())
+}
+
+/// Get the test output nodes for the given streams. Custom output streams are created by the
+/// registry.
+pub fn tests(streams: &[Stream], registry: &Registry) -> VecMap<Box<TestNode>> {
+    let mut tests: VecMap<Box<TestNode>> = VecMap::new();
+
+    for stream in streams.iter() {
+        match stream.kind {
+            Input => (),
+            Output => {
+                tests.insert(stream.node, Box::new(TestOutputNode::with_data(&stream.data)));
+            },
+            Image => {
+                tests.insert(stream.node, Box::new(TestImageNode::with_data(&stream.data, 30, 18)));
+            },
+            StreamKind::Custom(ref name) => {
+                if let Some(test) = registry.create_test(name, stream) {
+                    tests.insert(stream.node, test);
+                }
+            },
+        };
+    }
+
+    tests
+}
+
+/// Pick a seed for generating test streams based on the current time.
+fn random_seed() -> u32 {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(time) => (time.as_secs() as u32) ^ time.subsec_nanos(),
+        Err(_) => 0,
+    }
+}
+
+/// Read the entire contents of a spec file.
+fn read_file(filename: &str) -> Result<String, SpecError> {
+    let mut src = String::new();
+    match File::open(&Path::new(filename)) {
+        Ok(mut file) => match file.read_to_string(&mut src) {
+            Ok(_) => Ok(src),
+            Err(_) => Err(ReadFileFailed),
+        },
+        Err(_) => Err(ReadFileFailed),
+    }
+}
+
+#[test]
+fn test_from_segment() {
+    for segment in library::SEGMENTS.iter() {
+        let spec = Spec::from_segment(segment.id, Save::new()).ok().unwrap();
+        assert_eq!(spec.name, segment.name);
+        assert_eq!(spec.layout.len(), NUM_NODES);
+        assert!(spec.streams.iter().any(|s| s.kind != Input));
+
+        // Inputs enter the top row and outputs leave the bottom row through a compute node.
+        for stream in spec.streams.iter() {
+            let node = match stream.kind {
+                Input => stream.node,
+                Output => NUM_NODES - 4 + stream.node,
+                _ => continue,
+            };
+            assert_eq!(spec.layout[node], Compute, "{} {}", segment.id, stream.name);
+        }
+    }
+
+    assert!(Spec::from_segment("99999", Save::new()).is_err());
+}
+
+#[test]
+fn test_set_seed() {
+    let mut spec = Spec::from_segment("10981", Save::new()).ok().unwrap();
+    spec.set_seed(1).ok().unwrap();
+    let first = spec.streams.clone();
+
+    spec.set_seed(2).ok().unwrap();
+    assert!(spec.streams != first);
+
+    spec.set_seed(1).ok().unwrap();
+    assert_eq!(spec.seed(), Some(1));
+    assert_eq!(spec.streams, first);
+    assert_eq!(spec.streams(1).ok().unwrap(), first);
+}
+
+#[test]
+fn test_builder() {
+    let spec = SpecBuilder::new()
+        .tile(4, Memory)
+        .generator(|seed| Ok(vec![Stream::new(Input, "IN", 0, vec![seed as isize])]))
+        .build(Save::new()).ok().unwrap();
+
+    assert_eq!(spec.layout[4], Memory);
+    assert_eq!(spec.layout[5], Compute);
+    assert_eq!(spec.streams(7).ok().unwrap()[0].data, vec![7]);
+
+    let spec = SpecBuilder::new().output("OUT", 2, vec![1]).build(Save::new()).ok().unwrap();
+    assert_eq!(spec.seed(), None);
+    assert_eq!(spec.streams(7).ok().unwrap()[0].kind, Output);
+
+    // Errors from the generator aren't hidden.
+    let spec = SpecBuilder::new()
+        .generator(|_| Err(GetStreamsFailed))
+        .build(Save::new());
+    assert!(spec.is_err());
+}
+
+#[test]
+fn test_format_from_filename() {
+    assert_eq!(Format::from_filename("spec.lua"), Format::Lua);
+    assert_eq!(Format::from_filename("spec.toml"), Format::Toml);
+    assert_eq!(Format::from_filename("dir/spec.json"), Format::Json);
+    assert_eq!(Format::from_filename("spec"), Format::Lua);
+}
@@ -0,0 +1,38 @@
+-- SEGMENT 00150: SELF-TEST DIAGNOSTIC
+
+function get_name()
+    return "SELF-TEST DIAGNOSTIC"
+end
+
+function get_description()
+    return {
+        "> READ A VALUE FROM IN.X AND",
+        "  WRITE THE VALUE TO OUT.X",
+        "> READ A VALUE FROM IN.A AND",
+        "  WRITE THE VALUE TO OUT.A",
+    }
+end
+
+function get_streams()
+    local input_x = {}
+    local input_a = {}
+    for i = 1, 39 do
+        input_x[i] = math.random(10, 99)
+        input_a[i] = math.random(10, 99)
+    end
+
+    return {
+        { STREAM_INPUT, "IN.X", 0, input_x },
+        { STREAM_INPUT, "IN.A", 3, input_a },
+        { STREAM_OUTPUT, "OUT.X", 0, input_x },
+        { STREAM_OUTPUT, "OUT.A", 3, input_a },
+    }
+end
+
+function get_layout()
+    return {
+        TILE_COMPUTE, TILE_DAMAGED, TILE_COMPUTE, TILE_COMPUTE,
+        TILE_COMPUTE, TILE_DAMAGED, TILE_COMPUTE, TILE_COMPUTE,
+        TILE_COMPUTE, TILE_COMPUTE, TILE_DAMAGED, TILE_COMPUTE,
+    }
+end
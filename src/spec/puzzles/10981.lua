@@ -0,0 +1,35 @@
+-- SEGMENT 10981: SIGNAL AMPLIFIER
+
+function get_name()
+    return "SIGNAL AMPLIFIER"
+end
+
+function get_description()
+    return {
+        "> READ A VALUE FROM IN.A",
+        "> DOUBLE THE VALUE",
+        "> WRITE THE VALUE TO OUT.A",
+    }
+end
+
+function get_streams()
+    local input = {}
+    local output = {}
+    for i = 1, 39 do
+        input[i] = math.random(10, 99)
+        output[i] = input[i] * 2
+    end
+
+    return {
+        { STREAM_INPUT, "IN.A", 1, input },
+        { STREAM_OUTPUT, "OUT.A", 2, output },
+    }
+end
+
+function get_layout()
+    return {
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_DAMAGED,
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+    }
+end
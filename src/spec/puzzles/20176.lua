@@ -0,0 +1,41 @@
+-- SEGMENT 20176: DIFFERENTIAL CONVERTER
+
+function get_name()
+    return "DIFFERENTIAL CONVERTER"
+end
+
+function get_description()
+    return {
+        "> READ VALUES FROM IN.A AND IN.B",
+        "> WRITE IN.A - IN.B TO OUT.P",
+        "> WRITE IN.B - IN.A TO OUT.N",
+    }
+end
+
+function get_streams()
+    local input_a = {}
+    local input_b = {}
+    local output_p = {}
+    local output_n = {}
+    for i = 1, 39 do
+        input_a[i] = math.random(10, 99)
+        input_b[i] = math.random(10, 99)
+        output_p[i] = input_a[i] - input_b[i]
+        output_n[i] = input_b[i] - input_a[i]
+    end
+
+    return {
+        { STREAM_INPUT, "IN.A", 1, input_a },
+        { STREAM_INPUT, "IN.B", 2, input_b },
+        { STREAM_OUTPUT, "OUT.P", 1, output_p },
+        { STREAM_OUTPUT, "OUT.N", 2, output_n },
+    }
+end
+
+function get_layout()
+    return {
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+        TILE_DAMAGED, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+    }
+end
@@ -0,0 +1,44 @@
+-- SEGMENT 21340: SIGNAL COMPARATOR
+
+function get_name()
+    return "SIGNAL COMPARATOR"
+end
+
+function get_description()
+    return {
+        "> READ A VALUE FROM IN",
+        "> WRITE 1 TO OUT.G IF IN > 0",
+        "> WRITE 1 TO OUT.E IF IN = 0",
+        "> WRITE 1 TO OUT.L IF IN < 0",
+        "> WHEN A 1 IS NOT WRITTEN TO AN",
+        "  OUTPUT, WRITE A 0 INSTEAD",
+    }
+end
+
+function get_streams()
+    local input = {}
+    local output_g = {}
+    local output_e = {}
+    local output_l = {}
+    for i = 1, 39 do
+        input[i] = math.random(-2, 2)
+        output_g[i] = input[i] > 0 and 1 or 0
+        output_e[i] = input[i] == 0 and 1 or 0
+        output_l[i] = input[i] < 0 and 1 or 0
+    end
+
+    return {
+        { STREAM_INPUT, "IN", 0, input },
+        { STREAM_OUTPUT, "OUT.G", 1, output_g },
+        { STREAM_OUTPUT, "OUT.E", 2, output_e },
+        { STREAM_OUTPUT, "OUT.L", 3, output_l },
+    }
+end
+
+function get_layout()
+    return {
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+    }
+end
@@ -0,0 +1,49 @@
+-- SEGMENT 22280: SIGNAL MULTIPLEXER
+
+function get_name()
+    return "SIGNAL MULTIPLEXER"
+end
+
+function get_description()
+    return {
+        "> READ VALUES FROM IN.A AND IN.B",
+        "> READ A VALUE FROM IN.S",
+        "> WRITE IN.A WHEN IN.S = -1",
+        "> WRITE IN.B WHEN IN.S = 1",
+        "> WRITE IN.A + IN.B WHEN IN.S = 0",
+    }
+end
+
+function get_streams()
+    local input_a = {}
+    local input_s = {}
+    local input_b = {}
+    local output = {}
+    for i = 1, 39 do
+        input_a[i] = math.random(-30, 0)
+        input_s[i] = math.random(-1, 1)
+        input_b[i] = math.random(0, 30)
+        if input_s[i] < 0 then
+            output[i] = input_a[i]
+        elseif input_s[i] > 0 then
+            output[i] = input_b[i]
+        else
+            output[i] = input_a[i] + input_b[i]
+        end
+    end
+
+    return {
+        { STREAM_INPUT, "IN.A", 1, input_a },
+        { STREAM_INPUT, "IN.S", 2, input_s },
+        { STREAM_INPUT, "IN.B", 3, input_b },
+        { STREAM_OUTPUT, "OUT", 2, output },
+    }
+end
+
+function get_layout()
+    return {
+        TILE_DAMAGED, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+    }
+end
@@ -0,0 +1,41 @@
+-- SEGMENT 30647: SEQUENCE GENERATOR
+
+function get_name()
+    return "SEQUENCE GENERATOR"
+end
+
+function get_description()
+    return {
+        "> SEQUENCE FROM IN.A AND IN.B",
+        "> WRITE THE LESSER VALUE",
+        "> WRITE THE GREATER VALUE",
+        "> WRITE 0 TO END THE SEQUENCE",
+    }
+end
+
+function get_streams()
+    local input_a = {}
+    local input_b = {}
+    local output = {}
+    for i = 1, 13 do
+        input_a[i] = math.random(10, 99)
+        input_b[i] = math.random(10, 99)
+        table.insert(output, math.min(input_a[i], input_b[i]))
+        table.insert(output, math.max(input_a[i], input_b[i]))
+        table.insert(output, 0)
+    end
+
+    return {
+        { STREAM_INPUT, "IN.A", 1, input_a },
+        { STREAM_INPUT, "IN.B", 2, input_b },
+        { STREAM_OUTPUT, "OUT", 2, output },
+    }
+end
+
+function get_layout()
+    return {
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_DAMAGED,
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+    }
+end
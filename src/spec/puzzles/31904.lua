@@ -0,0 +1,65 @@
+-- SEGMENT 31904: SEQUENCE COUNTER
+
+-- Generate sequences of random length that fit in one input stream, including their zero
+-- terminators.
+local function make_sequences(min_length, max_length, low, high)
+    local sequences = {}
+    local total = 0
+    while true do
+        local length = math.random(min_length, max_length)
+        if total + length + 1 > 39 then
+            break
+        end
+
+        local sequence = {}
+        for i = 1, length do
+            sequence[i] = math.random(low, high)
+        end
+        table.insert(sequences, sequence)
+        total = total + length + 1
+    end
+    return sequences
+end
+
+function get_name()
+    return "SEQUENCE COUNTER"
+end
+
+function get_description()
+    return {
+        "> SEQUENCES ARE ZERO-TERMINATED",
+        "> READ A SEQUENCE FROM IN",
+        "> WRITE THE SUM TO OUT.S",
+        "> WRITE THE LENGTH TO OUT.L",
+    }
+end
+
+function get_streams()
+    local input = {}
+    local output_s = {}
+    local output_l = {}
+    for _, sequence in ipairs(make_sequences(0, 5, 10, 99)) do
+        local sum = 0
+        for _, value in ipairs(sequence) do
+            table.insert(input, value)
+            sum = sum + value
+        end
+        table.insert(input, 0)
+        table.insert(output_s, sum)
+        table.insert(output_l, #sequence)
+    end
+
+    return {
+        { STREAM_INPUT, "IN", 1, input },
+        { STREAM_OUTPUT, "OUT.S", 1, output_s },
+        { STREAM_OUTPUT, "OUT.L", 2, output_l },
+    }
+end
+
+function get_layout()
+    return {
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+    }
+end
@@ -0,0 +1,43 @@
+-- SEGMENT 32050: SIGNAL EDGE DETECTOR
+
+function get_name()
+    return "SIGNAL EDGE DETECTOR"
+end
+
+function get_description()
+    return {
+        "> READ A VALUE FROM IN",
+        "> COMPARE VALUE TO PREVIOUS VALUE",
+        "> WRITE 1 IF CHANGED BY 10 OR MORE",
+        "> IF NOT TRUE, WRITE 0 INSTEAD",
+        "> THE FIRST VALUE IS COMPARED TO 0",
+    }
+end
+
+function get_streams()
+    local input = {}
+    local output = {}
+    local previous = 0
+    for i = 1, 39 do
+        if math.random(1, 3) == 1 then
+            input[i] = math.random(0, 60)
+        else
+            input[i] = math.max(0, previous + math.random(-9, 9))
+        end
+        output[i] = math.abs(input[i] - previous) >= 10 and 1 or 0
+        previous = input[i]
+    end
+
+    return {
+        { STREAM_INPUT, "IN", 1, input },
+        { STREAM_OUTPUT, "OUT", 2, output },
+    }
+end
+
+function get_layout()
+    return {
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+        TILE_COMPUTE, TILE_COMPUTE, TILE_DAMAGED, TILE_COMPUTE,
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+    }
+end
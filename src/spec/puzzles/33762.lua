@@ -0,0 +1,51 @@
+-- SEGMENT 33762: INTERRUPT HANDLER
+
+function get_name()
+    return "INTERRUPT HANDLER"
+end
+
+function get_description()
+    return {
+        "> READ FROM IN.1 THROUGH IN.4",
+        "> WRITE THE INPUT NUMBER WHEN",
+        "  THE VALUE GOES FROM 0 TO 1",
+        "> TWO INTERRUPTS WILL NEVER",
+        "  CHANGE IN THE SAME INPUT CYCLE",
+    }
+end
+
+function get_streams()
+    local inputs = { {}, {}, {}, {} }
+    local state = { 0, 0, 0, 0 }
+    local output = {}
+    for i = 1, 39 do
+        local changed = 0
+        if math.random(1, 3) > 1 then
+            local port = math.random(1, 4)
+            state[port] = 1 - state[port]
+            if state[port] == 1 then
+                changed = port
+            end
+        end
+        for port = 1, 4 do
+            inputs[port][i] = state[port]
+        end
+        output[i] = changed
+    end
+
+    return {
+        { STREAM_INPUT, "IN.1", 0, inputs[1] },
+        { STREAM_INPUT, "IN.2", 1, inputs[2] },
+        { STREAM_INPUT, "IN.3", 2, inputs[3] },
+        { STREAM_INPUT, "IN.4", 3, inputs[4] },
+        { STREAM_OUTPUT, "OUT", 2, output },
+    }
+end
+
+function get_layout()
+    return {
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+        TILE_DAMAGED, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+    }
+end
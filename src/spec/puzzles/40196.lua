@@ -0,0 +1,46 @@
+-- SEGMENT 40196: SIGNAL PATTERN DETECTOR
+
+function get_name()
+    return "SIGNAL PATTERN DETECTOR"
+end
+
+function get_description()
+    return {
+        "> READ A VALUE FROM IN",
+        "> LOOK FOR THE PATTERN 0,0,0",
+        "> WRITE 1 WHEN THE PATTERN IS FOUND",
+        "> IF NOT TRUE, WRITE 0 INSTEAD",
+    }
+end
+
+function get_streams()
+    local input = {}
+    local output = {}
+    local zeros = 0
+    for i = 1, 39 do
+        if math.random(1, 2) == 1 then
+            input[i] = 0
+        else
+            input[i] = math.random(1, 30)
+        end
+        if input[i] == 0 then
+            zeros = zeros + 1
+        else
+            zeros = 0
+        end
+        output[i] = zeros >= 3 and 1 or 0
+    end
+
+    return {
+        { STREAM_INPUT, "IN", 1, input },
+        { STREAM_OUTPUT, "OUT", 2, output },
+    }
+end
+
+function get_layout()
+    return {
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_DAMAGED,
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+    }
+end
@@ -0,0 +1,63 @@
+-- SEGMENT 41427: SEQUENCE PEAK DETECTOR
+
+-- Generate sequences of random length that fit in one input stream, including their zero
+-- terminators.
+local function make_sequences(min_length, max_length, low, high)
+    local sequences = {}
+    local total = 0
+    while true do
+        local length = math.random(min_length, max_length)
+        if total + length + 1 > 39 then
+            break
+        end
+
+        local sequence = {}
+        for i = 1, length do
+            sequence[i] = math.random(low, high)
+        end
+        table.insert(sequences, sequence)
+        total = total + length + 1
+    end
+    return sequences
+end
+
+function get_name()
+    return "SEQUENCE PEAK DETECTOR"
+end
+
+function get_description()
+    return {
+        "> SEQUENCES ARE ZERO-TERMINATED",
+        "> READ A SEQUENCE FROM IN",
+        "> WRITE THE MIN VALUE TO OUT.I",
+        "> WRITE THE MAX VALUE TO OUT.A",
+    }
+end
+
+function get_streams()
+    local input = {}
+    local output_i = {}
+    local output_a = {}
+    for _, sequence in ipairs(make_sequences(1, 6, 10, 99)) do
+        for _, value in ipairs(sequence) do
+            table.insert(input, value)
+        end
+        table.insert(input, 0)
+        table.insert(output_i, math.min(table.unpack(sequence)))
+        table.insert(output_a, math.max(table.unpack(sequence)))
+    end
+
+    return {
+        { STREAM_INPUT, "IN", 1, input },
+        { STREAM_OUTPUT, "OUT.I", 1, output_i },
+        { STREAM_OUTPUT, "OUT.A", 2, output_a },
+    }
+end
+
+function get_layout()
+    return {
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+    }
+end
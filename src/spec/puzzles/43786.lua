@@ -0,0 +1,38 @@
+-- SEGMENT 43786: SIGNAL MULTIPLIER
+
+function get_name()
+    return "SIGNAL MULTIPLIER"
+end
+
+function get_description()
+    return {
+        "> READ VALUES FROM IN.A AND IN.B",
+        "> MULTIPLY THE VALUES",
+        "> WRITE THE RESULT TO OUT",
+    }
+end
+
+function get_streams()
+    local input_a = {}
+    local input_b = {}
+    local output = {}
+    for i = 1, 39 do
+        input_a[i] = math.random(0, 9)
+        input_b[i] = math.random(0, 9)
+        output[i] = input_a[i] * input_b[i]
+    end
+
+    return {
+        { STREAM_INPUT, "IN.A", 1, input_a },
+        { STREAM_INPUT, "IN.B", 2, input_b },
+        { STREAM_OUTPUT, "OUT", 2, output },
+    }
+end
+
+function get_layout()
+    return {
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+        TILE_MEMORY, TILE_COMPUTE, TILE_COMPUTE, TILE_MEMORY,
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+    }
+end
@@ -0,0 +1,34 @@
+-- SEGMENT 50370: IMAGE TEST PATTERN 1
+
+function get_name()
+    return "IMAGE TEST PATTERN 1"
+end
+
+function get_description()
+    return {
+        "> DRAW THE IMAGE ON THE DISPLAY",
+        "> THE IMAGE IS 30 BY 18 PIXELS",
+        "  AND EVERY PIXEL IS WHITE",
+    }
+end
+
+function get_streams()
+    local image = {}
+    for y = 0, 17 do
+        for x = 0, 29 do
+            image[y * 30 + x + 1] = 3
+        end
+    end
+
+    return {
+        { STREAM_IMAGE, "IMAGE", 2, image },
+    }
+end
+
+function get_layout()
+    return {
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+    }
+end
@@ -0,0 +1,34 @@
+-- SEGMENT 51781: IMAGE TEST PATTERN 2
+
+function get_name()
+    return "IMAGE TEST PATTERN 2"
+end
+
+function get_description()
+    return {
+        "> DRAW THE IMAGE ON THE DISPLAY",
+        "> THE IMAGE IS A CHECKERBOARD OF",
+        "  WHITE AND BLACK PIXELS",
+    }
+end
+
+function get_streams()
+    local image = {}
+    for y = 0, 17 do
+        for x = 0, 29 do
+            image[y * 30 + x + 1] = (x + y) % 2 == 0 and 3 or 0
+        end
+    end
+
+    return {
+        { STREAM_IMAGE, "IMAGE", 2, image },
+    }
+end
+
+function get_layout()
+    return {
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+    }
+end
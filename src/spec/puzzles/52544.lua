@@ -0,0 +1,54 @@
+-- SEGMENT 52544: EXPOSURE MASK VIEWER
+
+function get_name()
+    return "EXPOSURE MASK VIEWER"
+end
+
+function get_description()
+    return {
+        "> READ VALUES FROM IN.X, IN.Y,",
+        "  IN.W AND IN.H",
+        "> DRAW A WHITE RECTANGLE AT X,Y",
+        "  THAT IS W WIDE AND H HIGH",
+        "> THE BACKGROUND IS BLACK",
+    }
+end
+
+function get_streams()
+    local image = {}
+    for i = 1, 30 * 18 do
+        image[i] = 0
+    end
+
+    local input_x = {}
+    local input_y = {}
+    local input_w = {}
+    local input_h = {}
+    for i = 1, 9 do
+        input_w[i] = math.random(3, 8)
+        input_h[i] = math.random(2, 6)
+        input_x[i] = math.random(0, 30 - input_w[i])
+        input_y[i] = math.random(0, 18 - input_h[i])
+        for y = input_y[i], input_y[i] + input_h[i] - 1 do
+            for x = input_x[i], input_x[i] + input_w[i] - 1 do
+                image[y * 30 + x + 1] = 3
+            end
+        end
+    end
+
+    return {
+        { STREAM_INPUT, "IN.X", 0, input_x },
+        { STREAM_INPUT, "IN.Y", 1, input_y },
+        { STREAM_INPUT, "IN.W", 2, input_w },
+        { STREAM_INPUT, "IN.H", 3, input_h },
+        { STREAM_IMAGE, "IMAGE", 2, image },
+    }
+end
+
+function get_layout()
+    return {
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_DAMAGED,
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+    }
+end
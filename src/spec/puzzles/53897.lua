@@ -0,0 +1,45 @@
+-- SEGMENT 53897: HISTOGRAM VIEWER
+
+function get_name()
+    return "HISTOGRAM VIEWER"
+end
+
+function get_description()
+    return {
+        "> READ A VALUE FROM IN",
+        "> DRAW A WHITE COLUMN THAT HIGH",
+        "  FROM THE BOTTOM OF THE DISPLAY",
+        "> EACH VALUE IS THE NEXT COLUMN",
+    }
+end
+
+function get_streams()
+    local image = {}
+    for i = 1, 30 * 18 do
+        image[i] = 0
+    end
+
+    -- The heights wander up and down like a histogram.
+    local input = {}
+    local height = math.random(1, 17)
+    for x = 0, 29 do
+        height = math.max(1, math.min(17, height + math.random(-3, 3)))
+        input[x + 1] = height
+        for y = 18 - height, 17 do
+            image[y * 30 + x + 1] = 3
+        end
+    end
+
+    return {
+        { STREAM_INPUT, "IN", 1, input },
+        { STREAM_IMAGE, "IMAGE", 2, image },
+    }
+end
+
+function get_layout()
+    return {
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+        TILE_DAMAGED, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+    }
+end
@@ -0,0 +1,47 @@
+-- SEGMENT 60099: SIGNAL WINDOW FILTER
+
+function get_name()
+    return "SIGNAL WINDOW FILTER"
+end
+
+function get_description()
+    return {
+        "> READ A VALUE FROM IN",
+        "> WRITE THE SUM OF THE LAST 3",
+        "  VALUES TO OUT.3",
+        "> WRITE THE SUM OF THE LAST 5",
+        "  VALUES TO OUT.5",
+        "> MISSING VALUES ARE 0",
+    }
+end
+
+function get_streams()
+    local input = {}
+    local output_3 = {}
+    local output_5 = {}
+    for i = 1, 39 do
+        input[i] = math.random(10, 99)
+        output_3[i] = 0
+        output_5[i] = 0
+        for j = math.max(1, i - 4), i do
+            if j > i - 3 then
+                output_3[i] = output_3[i] + input[j]
+            end
+            output_5[i] = output_5[i] + input[j]
+        end
+    end
+
+    return {
+        { STREAM_INPUT, "IN", 1, input },
+        { STREAM_OUTPUT, "OUT.3", 1, output_3 },
+        { STREAM_OUTPUT, "OUT.5", 2, output_5 },
+    }
+end
+
+function get_layout()
+    return {
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+        TILE_MEMORY, TILE_COMPUTE, TILE_COMPUTE, TILE_MEMORY,
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+    }
+end
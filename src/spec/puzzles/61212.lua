@@ -0,0 +1,42 @@
+-- SEGMENT 61212: SIGNAL DIVIDER
+
+function get_name()
+    return "SIGNAL DIVIDER"
+end
+
+function get_description()
+    return {
+        "> READ VALUES FROM IN.A AND IN.B",
+        "> DIVIDE IN.A BY IN.B",
+        "> WRITE THE QUOTIENT TO OUT.Q",
+        "> WRITE THE REMAINDER TO OUT.R",
+    }
+end
+
+function get_streams()
+    local input_a = {}
+    local input_b = {}
+    local output_q = {}
+    local output_r = {}
+    for i = 1, 39 do
+        input_a[i] = math.random(10, 999)
+        input_b[i] = math.random(1, 99)
+        output_q[i] = math.floor(input_a[i] / input_b[i])
+        output_r[i] = input_a[i] % input_b[i]
+    end
+
+    return {
+        { STREAM_INPUT, "IN.A", 1, input_a },
+        { STREAM_INPUT, "IN.B", 2, input_b },
+        { STREAM_OUTPUT, "OUT.Q", 1, output_q },
+        { STREAM_OUTPUT, "OUT.R", 2, output_r },
+    }
+end
+
+function get_layout()
+    return {
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+        TILE_MEMORY, TILE_COMPUTE, TILE_COMPUTE, TILE_MEMORY,
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+    }
+end
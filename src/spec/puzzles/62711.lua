@@ -0,0 +1,42 @@
+-- SEGMENT 62711: SEQUENCE INDEXER
+
+function get_name()
+    return "SEQUENCE INDEXER"
+end
+
+function get_description()
+    return {
+        "> READ 10 VALUES FROM IN.V",
+        "> READ AN INDEX FROM IN.X",
+        "> WRITE THE VALUE AT THAT INDEX",
+        "  TO OUT, STARTING FROM 0",
+    }
+end
+
+function get_streams()
+    local input_v = {}
+    for i = 1, 10 do
+        input_v[i] = math.random(100, 999)
+    end
+
+    local input_x = {}
+    local output = {}
+    for i = 1, 39 do
+        input_x[i] = math.random(0, 9)
+        output[i] = input_v[input_x[i] + 1]
+    end
+
+    return {
+        { STREAM_INPUT, "IN.V", 1, input_v },
+        { STREAM_INPUT, "IN.X", 2, input_x },
+        { STREAM_OUTPUT, "OUT", 1, output },
+    }
+end
+
+function get_layout()
+    return {
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+        TILE_MEMORY, TILE_COMPUTE, TILE_COMPUTE, TILE_MEMORY,
+    }
+end
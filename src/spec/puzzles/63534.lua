@@ -0,0 +1,64 @@
+-- SEGMENT 63534: SEQUENCE SORTER
+
+-- Generate sequences of random length that fit in one input stream, including their zero
+-- terminators.
+local function make_sequences(min_length, max_length, low, high)
+    local sequences = {}
+    local total = 0
+    while true do
+        local length = math.random(min_length, max_length)
+        if total + length + 1 > 39 then
+            break
+        end
+
+        local sequence = {}
+        for i = 1, length do
+            sequence[i] = math.random(low, high)
+        end
+        table.insert(sequences, sequence)
+        total = total + length + 1
+    end
+    return sequences
+end
+
+function get_name()
+    return "SEQUENCE SORTER"
+end
+
+function get_description()
+    return {
+        "> SEQUENCES ARE ZERO-TERMINATED",
+        "> READ A SEQUENCE FROM IN",
+        "> SORT THE SEQUENCE",
+        "> WRITE THE RESULT TO OUT",
+    }
+end
+
+function get_streams()
+    local input = {}
+    local output = {}
+    for _, sequence in ipairs(make_sequences(1, 6, 10, 99)) do
+        for _, value in ipairs(sequence) do
+            table.insert(input, value)
+        end
+        table.insert(input, 0)
+        table.sort(sequence)
+        for _, value in ipairs(sequence) do
+            table.insert(output, value)
+        end
+        table.insert(output, 0)
+    end
+
+    return {
+        { STREAM_INPUT, "IN", 1, input },
+        { STREAM_OUTPUT, "OUT", 2, output },
+    }
+end
+
+function get_layout()
+    return {
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+        TILE_MEMORY, TILE_COMPUTE, TILE_COMPUTE, TILE_MEMORY,
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+    }
+end
@@ -0,0 +1,46 @@
+-- SEGMENT 70601: STORED IMAGE DECODER
+
+function get_name()
+    return "STORED IMAGE DECODER"
+end
+
+function get_description()
+    return {
+        "> READ PAIRS OF VALUES FROM IN",
+        "> EACH PAIR IS A COUNT AND A",
+        "  COLOR FROM 0 TO 3",
+        "> DRAW THAT MANY PIXELS OF THE",
+        "  COLOR, LEFT TO RIGHT AND TOP",
+        "  TO BOTTOM",
+    }
+end
+
+function get_streams()
+    local input = {}
+    local image = {}
+    local color = math.random(0, 3)
+    while #image < 30 * 18 do
+        local count = math.min(math.random(10, 50), 30 * 18 - #image)
+
+        -- Each run has a different color than the one before it.
+        color = (color + math.random(1, 3)) % 4
+        table.insert(input, count)
+        table.insert(input, color)
+        for i = 1, count do
+            table.insert(image, color)
+        end
+    end
+
+    return {
+        { STREAM_INPUT, "IN", 1, input },
+        { STREAM_IMAGE, "IMAGE", 2, image },
+    }
+end
+
+function get_layout()
+    return {
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+        TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE, TILE_COMPUTE,
+    }
+end
@@ -0,0 +1,124 @@
+//! Custom tile and stream kinds that can be used by specs.
+
+use std::collections::HashMap;
+use node::{Node, TestNode};
+use super::Stream;
+
+/// A function that creates the node for a custom tile.
+pub type TileFactory = Box<Fn() -> Box<Node>>;
+
+/// A function that creates the node which supplies a custom input stream.
+pub type InputFactory = Box<Fn(&Stream) -> Box<Node>>;
+
+/// A function that creates the test node which checks a custom output stream.
+pub type TestFactory = Box<Fn(&Stream) -> Box<TestNode>>;
+
+/// A set of custom tile and stream kinds, identified by name. Specs refer to custom tiles with
+/// `Tile::Custom` and to custom streams with `StreamKind::Custom`, using the names that they were
+/// registered with. This allows applications to provide their own nodes without changing the
+/// `spec` module.
+///
+/// # Example
+///
+/// ```
+/// use tis_100::node::DamagedExecutionNode;
+/// use tis_100::spec::{Registry, SpecBuilder, PuzzleSpec, Tile};
+/// use tis_100::save::Save;
+///
+/// let mut registry = Registry::new();
+/// registry.register_tile("BROKEN", || Box::new(DamagedExecutionNode));
+///
+/// let spec = SpecBuilder::new()
+///     .registry(registry)
+///     .tile(3, Tile::Custom("BROKEN".to_string()))
+///     .build(Save::new()).ok().unwrap();
+///
+/// assert!(spec.registry().unwrap().has_tile("BROKEN"));
+/// ```
+pub struct Registry {
+    tiles: HashMap<String, TileFactory>,
+    inputs: HashMap<String, InputFactory>,
+    tests: HashMap<String, TestFactory>,
+}
+
+impl Registry {
+    /// Construct a new, empty `Registry`.
+    pub fn new() -> Registry {
+        Registry {
+            tiles: HashMap::new(),
+            inputs: HashMap::new(),
+            tests: HashMap::new(),
+        }
+    }
+
+    /// Register a custom tile. The factory is called once for each position in the layout that
+    /// uses the tile.
+    pub fn register_tile<F>(&mut self, name: &str, factory: F) -> &mut Self
+        where F: Fn() -> Box<Node> + 'static
+    {
+        self.tiles.insert(name.to_string(), Box::new(factory));
+        self
+    }
+
+    /// Register a custom input stream. The node that is created is connected to the top of the
+    /// node in the top row that the stream refers to.
+    pub fn register_input<F>(&mut self, name: &str, factory: F) -> &mut Self
+        where F: Fn(&Stream) -> Box<Node> + 'static
+    {
+        self.inputs.insert(name.to_string(), Box::new(factory));
+        self
+    }
+
+    /// Register a custom output stream. The test node that is created is connected to the bottom
+    /// of the node in the bottom row that the stream refers to.
+    pub fn register_test<F>(&mut self, name: &str, factory: F) -> &mut Self
+        where F: Fn(&Stream) -> Box<TestNode> + 'static
+    {
+        self.tests.insert(name.to_string(), Box::new(factory));
+        self
+    }
+
+    /// Check if a custom tile has been registered.
+    pub fn has_tile(&self, name: &str) -> bool {
+        self.tiles.contains_key(name)
+    }
+
+    /// Check if a custom stream has been registered, either as an input or as an output.
+    pub fn has_stream(&self, name: &str) -> bool {
+        self.inputs.contains_key(name) || self.tests.contains_key(name)
+    }
+
+    /// Create the node for a custom tile.
+    pub fn create_node(&self, name: &str) -> Option<Box<Node>> {
+        self.tiles.get(name).map(|factory| factory())
+    }
+
+    /// Create the node for a custom input stream.
+    pub fn create_input(&self, name: &str, stream: &Stream) -> Option<Box<Node>> {
+        self.inputs.get(name).map(|factory| factory(stream))
+    }
+
+    /// Create the test node for a custom output stream.
+    pub fn create_test(&self, name: &str, stream: &Stream) -> Option<Box<TestNode>> {
+        self.tests.get(name).map(|factory| factory(stream))
+    }
+}
+
+#[test]
+fn test_registry() {
+    use node::{DamagedExecutionNode, TestInputNode, TestOutputNode};
+    use super::StreamKind;
+
+    let mut registry = Registry::new();
+    registry.register_tile("BROKEN", || Box::new(DamagedExecutionNode))
+            .register_input("CONST", |s| Box::new(TestInputNode::with_data(&s.data)))
+            .register_test("EXACT", |s| Box::new(TestOutputNode::with_data(&s.data)));
+
+    let stream = Stream::new(StreamKind::Custom("CONST".to_string()), "IN", 0, vec![1]);
+    assert!(registry.has_tile("BROKEN"));
+    assert!(!registry.has_tile("CONST"));
+    assert!(registry.has_stream("CONST") && registry.has_stream("EXACT"));
+    assert!(registry.create_node("BROKEN").is_some());
+    assert!(registry.create_input("CONST", &stream).is_some());
+    assert!(registry.create_test("CONST", &stream).is_none());
+}
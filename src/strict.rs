@@ -0,0 +1,122 @@
+//! Strict validation of save files against the limits of the game.
+//!
+//! The lexer quietly ignores the lines of a program past `NUM_LINES` and the characters of a line
+//! past `NUM_CHARS`, the emulator runs one more line than the game's nodes can hold, and a `Save`
+//! can have programs for any node. A save that runs in the emulator can therefore be one that the
+//! game would never produce, or one that loses code without a warning. `check_save` finds
+//! everything in the source of a save that the game wouldn't accept.
+
+use std::fmt::{Display, Formatter, Error};
+use io::NodeId;
+use lex::{lex_program, Line, NUM_CHARS};
+use save::split_nodes;
+use spec::Tile;
+
+/// The maximum number of lines per program in the game, which is one less than `NUM_LINES`.
+pub const GAME_LINES: usize = 15;
+
+/// A part of a save file that the game wouldn't accept.
+#[derive(Debug, PartialEq, Clone)]
+pub enum StrictError {
+    /// A line is longer than the game allows. Holds the number of characters on the line.
+    LineTooLong(usize),
+    /// A program has more lines than the game allows. Holds the number of lines.
+    TooManyLines(usize),
+    /// The node isn't part of the layout.
+    NodeOutsideLayout,
+    /// The node has a program, but its tile can't execute code.
+    ProgramOnTile(Tile),
+}
+
+use self::StrictError::*;
+
+impl Display for StrictError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            &LineTooLong(chars) => write!(f, "Line has {} characters, but the game allows {}", chars, NUM_CHARS),
+            &TooManyLines(lines) => write!(f, "Program has {} lines, but the game allows {}", lines, GAME_LINES),
+            &NodeOutsideLayout => f.write_str("The node doesn't exist"),
+            &ProgramOnTile(ref tile) => write!(f, "The program is on a {} tile, which can't execute code",
+                                               String::from(tile.clone())),
+        }
+    }
+}
+
+/// Every error found in a save file, with the node and the line of the file that it refers to,
+/// starting from 1.
+pub type StrictErrors = Vec<(NodeId, usize, StrictError)>;
+
+/// Check the source of a save file against the limits of the game, for a puzzle with the given
+/// layout. Errors about a whole node refer to the line of its `@` header, and errors are in order
+/// of their lines.
+///
+/// # Example
+///
+/// ```
+/// use tis_100::spec::Tile;
+/// use tis_100::strict::{check_save, StrictError};
+///
+/// let layout = vec![Tile::Compute, Tile::Damaged];
+/// let src = "@0\nMOV UP DOWN # passes values\n@1\nNOP\n@2\n";
+///
+/// assert_eq!(check_save(src, &layout), vec![
+///     (0, 2, StrictError::LineTooLong(27)),
+///     (1, 3, StrictError::ProgramOnTile(Tile::Damaged)),
+///     (2, 5, StrictError::NodeOutsideLayout),
+/// ]);
+/// ```
+pub fn check_save(src: &str, layout: &[Tile]) -> StrictErrors {
+    let mut errors = Vec::new();
+
+    for (node, header, node_src) in split_nodes(src) {
+        let has_code = lex_program(&node_src).iter().any(|&Line(_, _, ref words, _)| !words.is_empty());
+
+        match layout.get(node) {
+            None => errors.push((node, header, NodeOutsideLayout)),
+            Some(&Tile::Compute) => (),
+            Some(tile) if has_code => errors.push((node, header, ProgramOnTile(tile.clone()))),
+            Some(_) => (),
+        }
+
+        let lines = node_src.lines().collect::<Vec<_>>();
+
+        for (index, line) in lines.iter().enumerate() {
+            let chars = line.trim_end().chars().count();
+            if chars > NUM_CHARS {
+                errors.push((node, header + 1 + index, LineTooLong(chars)));
+            }
+        }
+
+        // The blank lines between nodes aren't part of the program.
+        let num_lines = lines.iter().rposition(|line| !line.trim().is_empty()).map_or(0, |last| last + 1);
+        if num_lines > GAME_LINES {
+            errors.push((node, header + 1 + GAME_LINES, TooManyLines(num_lines)));
+        }
+    }
+
+    errors.sort_by_key(|&(_, line, _)| line);
+    errors
+}
+
+#[test]
+fn test_check_save() {
+    let layout = vec![Tile::Compute, Tile::Memory, Tile::Compute];
+    let long_program = (0..17).map(|_| "NOP\n").collect::<String>();
+    let src = format!("@0\n{}\n\n@1\n# NOTES ONLY\n\n@2\nNOP\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n@3\n\n", long_program);
+
+    let errors = check_save(&src, &layout);
+    assert_eq!(errors, vec![
+        (0, 17, TooManyLines(17)),
+        (3, 44, NodeOutsideLayout),
+    ]);
+
+    // The game's limit is one line less than the emulator's.
+    let program = |lines: usize| format!("@0\n{}", (0..lines).map(|_| "NOP\n").collect::<String>());
+    assert_eq!(check_save(&program(GAME_LINES), &layout), vec![]);
+    assert_eq!(check_save(&program(GAME_LINES + 1), &layout), vec![(0, 17, TooManyLines(16))]);
+
+    // Memory tiles can have notes, but not code.
+    let errors = check_save("@1\nL:\nADD 1 # ONE CONSTANT\n", &layout);
+    assert_eq!(errors, vec![(1, 1, ProgramOnTile(Tile::Memory)), (1, 3, LineTooLong(20))]);
+    assert_eq!(errors[0].2.to_string(), "The program is on a memory tile, which can't execute code");
+}
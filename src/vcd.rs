@@ -0,0 +1,313 @@
+//! Export of TIS-100 runs as Value Change Dump files, which can be viewed as waveforms in tools
+//! like GTKWave.
+//!
+//! A `VcdWriter` is attached to a `Tis100` as a `Hook`, and samples the system at the end of every
+//! cycle. Each time unit of the dump is one cycle. Every node with a program has a signal for its
+//! ACC, BAK, PC and mode, and every connection on the bus has a signal for the value that is
+//! waiting to be read, and whether the sending node is blocked until it is read.
+
+use std::io::{self, Write};
+use core::Port;
+use io::NodeId;
+use machine::{Hook, Tis100, NUM_NODES, INPUT_0, OUTPUT_0, NUM_INPUTS, NUM_OUTPUTS};
+
+/// The number of bits in a register. Values are dumped as two's complement.
+const VALUE_BITS: usize = 11;
+
+/// The number of bits in the program counter.
+const PC_BITS: usize = 4;
+
+/// The source of a signal's value.
+#[derive(Debug, Clone, Copy)]
+enum Probe {
+    Acc(NodeId),
+    Bak(NodeId),
+    Pc(NodeId),
+    Mode(NodeId),
+    Value(NodeId, Port),
+    Blocked(NodeId, Port),
+}
+
+/// A variable in the dump.
+#[derive(Debug)]
+struct Signal {
+    scope: String,
+    name: String,
+    kind: &'static str,
+    width: usize,
+    code: String,
+    probe: Probe,
+}
+
+/// Writes a Value Change Dump of a `Tis100` to a writer, one cycle at a time.
+///
+/// # Example
+///
+/// ```
+/// use std::cell::RefCell;
+/// use std::rc::Rc;
+/// use tis_100::save::{Save, parse_save};
+/// use tis_100::spec::SpecBuilder;
+/// use tis_100::machine::Puzzle;
+/// use tis_100::vcd::VcdWriter;
+///
+/// let spec = SpecBuilder::new()
+///     .input("IN.A", 1, vec![1, 2, 3])
+///     .output("OUT.A", 1, vec![1, 2, 3])
+///     .build(Save::new()).ok().unwrap();
+///
+/// let save = parse_save("@1\nMOV UP DOWN\n@5\nMOV UP DOWN\n@9\nMOV UP DOWN\n").unwrap();
+/// let mut puzzle = Puzzle::new(&spec, &save, 0).ok().unwrap();
+///
+/// let vcd = Rc::new(RefCell::new(VcdWriter::new(Vec::new(), puzzle.cpu())));
+/// puzzle.add_hook(vcd.clone());
+/// puzzle.run();
+///
+/// let dump = String::from_utf8(vcd.borrow().get_ref().clone()).unwrap();
+/// assert!(dump.contains("$scope module node_5 $end"));
+/// assert!(dump.contains("$var integer 11 "));
+/// ```
+#[derive(Debug)]
+pub struct VcdWriter<W: Write> {
+    out: W,
+    signals: Vec<Signal>,
+    values: Vec<String>,
+    cycle: usize,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> VcdWriter<W> {
+    /// Construct a new `VcdWriter` for the nodes and connections of a `Tis100`, and write the
+    /// header and the initial values. Nodes without a program are left out.
+    pub fn new(out: W, cpu: &Tis100) -> VcdWriter<W> {
+        let mut signals = Vec::new();
+
+        for node in 0..NUM_NODES {
+            if cpu.program(node).map_or(true, |prog| prog.is_empty()) {
+                continue;
+            }
+
+            let scope = format!("node_{}", node);
+            signals.push(Signal::new(&scope, "acc", "integer", VALUE_BITS, Probe::Acc(node)));
+            signals.push(Signal::new(&scope, "bak", "integer", VALUE_BITS, Probe::Bak(node)));
+            signals.push(Signal::new(&scope, "pc", "integer", PC_BITS, Probe::Pc(node)));
+            signals.push(Signal::new(&scope, "mode", "string", 1, Probe::Mode(node)));
+        }
+
+        for (from, port, to) in cpu.bus().connections() {
+            let name = format!("{}_{}_{}", signal_name(from), format!("{:?}", port).to_lowercase(), signal_name(to));
+            signals.push(Signal::new("bus", &name, "integer", VALUE_BITS, Probe::Value(from, port)));
+            signals.push(Signal::new("bus", &format!("{}_blocked", name), "wire", 1, Probe::Blocked(from, port)));
+        }
+
+        for (index, signal) in signals.iter_mut().enumerate() {
+            signal.code = identifier(index);
+        }
+
+        let mut writer = VcdWriter {
+            out: out,
+            values: vec![String::new(); signals.len()],
+            signals: signals,
+            cycle: 0,
+            error: None,
+        };
+
+        let result = writer.write_header().and_then(|_| writer.write_values(cpu));
+        writer.record(result);
+        writer
+    }
+
+    /// Get a reference to the writer.
+    pub fn get_ref(&self) -> &W {
+        &self.out
+    }
+
+    /// Flush the writer, and return the first error that happened while writing the dump.
+    pub fn flush(&mut self) -> io::Result<()> {
+        let result = self.out.flush();
+        self.record(result);
+
+        match self.error.take() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// Consume the `VcdWriter` and return the writer, or the first error that happened while
+    /// writing the dump.
+    pub fn into_inner(self) -> io::Result<W> {
+        match self.error {
+            Some(err) => Err(err),
+            None => Ok(self.out),
+        }
+    }
+
+    /// Keep the first error, since hooks can't return one.
+    fn record(&mut self, result: io::Result<()>) {
+        if let Err(err) = result {
+            if self.error.is_none() {
+                self.error = Some(err);
+            }
+        }
+    }
+
+    /// Write the declarations of every signal.
+    fn write_header(&mut self) -> io::Result<()> {
+        writeln!(self.out, "$comment TIS-100 $end")?;
+        writeln!(self.out, "$timescale 1 ns $end")?;
+        writeln!(self.out, "$comment Each time unit is one cycle. $end")?;
+
+        let mut scope: Option<&str> = None;
+        for signal in self.signals.iter() {
+            if scope != Some(&signal.scope) {
+                if scope.is_some() {
+                    writeln!(self.out, "$upscope $end")?;
+                }
+                writeln!(self.out, "$scope module {} $end", signal.scope)?;
+                scope = Some(&signal.scope);
+            }
+
+            writeln!(self.out, "$var {} {} {} {} $end", signal.kind, signal.width, signal.code, signal.name)?;
+        }
+
+        if scope.is_some() {
+            writeln!(self.out, "$upscope $end")?;
+        }
+
+        writeln!(self.out, "$enddefinitions $end")?;
+        writeln!(self.out, "#0")?;
+        writeln!(self.out, "$dumpvars")
+    }
+
+    /// Write the value of every signal that changed since the last cycle.
+    fn write_values(&mut self, cpu: &Tis100) -> io::Result<()> {
+        for (index, signal) in self.signals.iter().enumerate() {
+            let value = signal.sample(cpu);
+            if value != self.values[index] {
+                writeln!(self.out, "{}", value)?;
+                self.values[index] = value;
+            }
+        }
+
+        if self.cycle == 0 {
+            writeln!(self.out, "$end")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<W: Write> Hook for VcdWriter<W> {
+    fn commit(&mut self, cpu: &Tis100) {
+        self.cycle += 1;
+
+        let cycle = self.cycle;
+        let result = writeln!(self.out, "#{}", cycle).and_then(|_| self.write_values(cpu));
+        self.record(result);
+    }
+}
+
+impl Signal {
+    fn new(scope: &str, name: &str, kind: &'static str, width: usize, probe: Probe) -> Signal {
+        Signal {
+            scope: scope.to_string(),
+            name: name.to_string(),
+            kind: kind,
+            width: width,
+            code: String::new(),
+            probe: probe,
+        }
+    }
+
+    /// Get the value change for the signal's current value.
+    fn sample(&self, cpu: &Tis100) -> String {
+        let value = match self.probe {
+            Probe::Acc(node) => cpu.registers(node).map(|r| binary(r.acc, VALUE_BITS)),
+            Probe::Bak(node) => cpu.registers(node).map(|r| binary(r.bak, VALUE_BITS)),
+            Probe::Pc(node) => cpu.registers(node).map(|r| binary(r.pc as isize, PC_BITS)),
+            Probe::Mode(node) => {
+                let mode = cpu.registers(node).map(|r| format!("{:?}", r.mode).to_uppercase());
+                return format!("s{} {}", mode.unwrap_or("X".to_string()), self.code);
+            },
+            Probe::Value(node, port) => cpu.bus().value(node, port).map(|v| binary(v, VALUE_BITS)),
+            Probe::Blocked(node, port) => {
+                let blocked = cpu.bus().is_blocked(node) && cpu.bus().value(node, port).is_some();
+                return format!("{}{}", if blocked { 1 } else { 0 }, self.code);
+            },
+        };
+
+        format!("b{} {}", value.unwrap_or("x".to_string()), self.code)
+    }
+}
+
+/// Format a value as two's complement binary with the given number of bits.
+fn binary(value: isize, bits: usize) -> String {
+    format!("{:b}", (value as usize) & ((1 << bits) - 1))
+}
+
+/// Get the short identifier for the signal with the given index, using the printable ASCII
+/// characters.
+fn identifier(mut index: usize) -> String {
+    let mut code = String::new();
+    loop {
+        code.push((b'!' + (index % 94) as u8) as char);
+        index /= 94;
+        if index == 0 {
+            return code;
+        }
+        index -= 1;
+    }
+}
+
+/// Get the name of a node that can be used in a signal name.
+fn signal_name(node: NodeId) -> String {
+    if node < NUM_NODES {
+        format!("node{}", node)
+    } else if node >= INPUT_0 && node < INPUT_0 + NUM_INPUTS {
+        format!("input{}", node - INPUT_0)
+    } else if node >= OUTPUT_0 && node < OUTPUT_0 + NUM_OUTPUTS {
+        format!("output{}", node - OUTPUT_0)
+    } else {
+        format!("n{}", node)
+    }
+}
+
+#[test]
+fn test_vcd_values() {
+    assert_eq!(binary(5, 11), "101");
+    assert_eq!(binary(-1, 11), "11111111111");
+    assert_eq!(binary(-999, 11), "10000011001");
+
+    assert_eq!(identifier(0), "!");
+    assert_eq!(identifier(93), "~");
+    assert_eq!(identifier(94), "!!");
+    assert_eq!(identifier(95), "\"!");
+}
+
+#[test]
+fn test_vcd_writer() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use machine::Puzzle;
+    use fixtures::pipeline;
+
+    let (spec, save) = pipeline();
+    let mut puzzle = Puzzle::new(&spec, &save, 0).ok().unwrap();
+
+    let vcd = Rc::new(RefCell::new(VcdWriter::new(Vec::new(), puzzle.cpu())));
+    puzzle.add_hook(vcd.clone());
+    puzzle.run();
+
+    let dump = String::from_utf8(vcd.borrow().get_ref().clone()).unwrap();
+    assert!(dump.contains("$var string 1 $ mode $end"));
+    assert!(dump.contains("$var wire 1 "));
+    assert!(dump.contains(" node1_down_node5_blocked $end"));
+    assert!(dump.contains(&format!("#{}\n", puzzle.cycles())));
+    assert!(!dump.contains(&format!("#{}\n", puzzle.cycles() + 1)));
+
+    // Node 1 reads the first input in the second cycle, and waits for node 5 to read it.
+    let cycle_2 = dump.split("#2\n").nth(1).unwrap().split("#3\n").next().unwrap();
+    assert!(cycle_2.contains("sWRTE $\n"));
+    assert!(cycle_2.contains("b1 5\n"));
+    assert!(cycle_2.contains("16\n"));
+}